@@ -0,0 +1,179 @@
+//! [![License: BSD-3-Clause](https://img.shields.io/badge/license-BSD--3--Clause-blue)](./LICENSE)
+//! [![cdumay_job_derive on crates.io](https://img.shields.io/crates/v/cdumay_job_derive)](https://crates.io/crates/cdumay_job_derive)
+//! [![cdumay_job_derive on docs.rs](https://docs.rs/cdumay_job_derive/badge.svg)](https://docs.rs/cdumay_job_derive)
+//! [![Source Code Repository](https://img.shields.io/badge/Code-On%20GitHub-blue?logo=GitHub)](https://github.com/cdumay/cdumay_job)
+//!
+//! The `cdumay_job_derive` crate provides procedural macros to implement the `cdumay_job` traits on
+//! any struct, where `define_task!` only generates a struct with a fixed set of fields.
+//!
+//! # Usage
+//!
+//! Enable the feature `derive` of `cdumay_job`:
+//!
+//! ```toml
+//! [dependencies]
+//! cdumay_job = { version = "1.0", features = ["derive"] }
+//! ```
+//!
+//! Then mark the fields holding the message, the status and the result. Fields named `message`,
+//! `status` and `result` are picked up without any attribute. Every other field is initialized
+//! using `Default::default()` by `TaskInfo::new`. The type of the message params is set using
+//! `#[task(params = Type)]` on the struct and the type of the task output using
+//! `#[task(output = Type)]`, both default to `cdumay_job::Value` (a re-export of `serde_value::Value`).
+//!
+//! ```rust
+//! use cdumay_job::{MessageBuilder, Status, TaskExec, TaskInfo};
+//!
+//! #[derive(TaskInfo)]
+//! pub struct Counter {
+//!     #[task(message)]
+//!     msg: cdumay_job::Message,
+//!     #[task(status)]
+//!     state: cdumay_job::Status,
+//!     result: cdumay_result::Result,
+//!     calls: usize,
+//! }
+//!
+//! impl TaskExec for Counter {
+//!     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+//!         self.calls += 1;
+//!         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+//!     }
+//! }
+//!
+//! let mut task = Counter::new(&MessageBuilder::new("counter".to_string()).build(), None);
+//! task.execute(None);
+//! assert_eq!(task.calls, 1);
+//! assert_eq!(task.status(), Status::Success);
+//! assert!(Counter::path().ends_with(".Counter"));
+//! ```
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+const ROLES: [&str; 3] = ["message", "status", "result"];
+
+/// Looks up the role (`message`, `status` or `result`) of a field, first using the `#[task(...)]`
+/// attribute and then the field name.
+fn field_role(field: &syn::Field) -> syn::Result<Option<String>> {
+    if let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("task")) {
+        let role: Ident = attr.parse_args()?;
+        return match ROLES.contains(&role.to_string().as_str()) {
+            true => Ok(Some(role.to_string())),
+            false => Err(syn::Error::new(
+                role.span(),
+                "expected one of `message`, `status` or `result`",
+            )),
+        };
+    }
+    Ok(field
+        .ident
+        .as_ref()
+        .map(|ident| ident.to_string())
+        .filter(|name| ROLES.contains(&name.as_str())))
+}
+
+/// Looks up the types of the params and of the output set using
+/// `#[task(params = Type, output = Type)]` on the struct.
+fn task_types(input: &DeriveInput) -> syn::Result<(syn::Type, syn::Type)> {
+    let mut params = None;
+    let mut output = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("task")) {
+        attr.parse_nested_meta(|meta| {
+            let slot = match meta.path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                Some("params") => &mut params,
+                Some("output") => &mut output,
+                _ => return Err(meta.error("expected `params = Type` or `output = Type`")),
+            };
+            *slot = Some(meta.value()?.parse()?);
+            Ok(())
+        })?;
+    }
+    Ok((
+        params.unwrap_or_else(|| syn::parse_quote!(cdumay_job::Value)),
+        output.unwrap_or_else(|| syn::parse_quote!(cdumay_job::Value)),
+    ))
+}
+
+fn expand_task_info(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let (params, output) = task_types(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new(input.span(), "TaskInfo can only be derived on structs with named fields")),
+        },
+        _ => return Err(syn::Error::new(input.span(), "TaskInfo can only be derived on structs")),
+    };
+
+    let mut message = None;
+    let mut status = None;
+    let mut result = None;
+    let mut others = vec![];
+    for field in fields {
+        let ident = field.ident.clone().unwrap();
+        let slot = match field_role(field)?.as_deref() {
+            Some("message") => &mut message,
+            Some("status") => &mut status,
+            Some("result") => &mut result,
+            _ => {
+                others.push(ident);
+                continue;
+            }
+        };
+        if slot.replace(ident).is_some() {
+            return Err(syn::Error::new(field.span(), "duplicate task field"));
+        }
+    }
+    let message = message.ok_or_else(|| syn::Error::new(input.span(), "missing a `#[task(message)]` field"))?;
+    let status = status.ok_or_else(|| syn::Error::new(input.span(), "missing a `#[task(status)]` field"))?;
+    let result = result.ok_or_else(|| syn::Error::new(input.span(), "missing a `#[task(result)]` field"))?;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics cdumay_job::TaskInfo for #name #ty_generics #where_clause {
+            type Params = #params;
+            type Output = #output;
+
+            fn new(msg: &cdumay_job::Message, result: Option<cdumay_result::Result>) -> Self {
+                Self {
+                    #message: msg.clone(),
+                    #status: cdumay_job::Status::Pending,
+                    #result: result.unwrap_or(msg.result.clone()),
+                    #(#others: Default::default(),)*
+                }
+            }
+            fn path() -> String {
+                format!("{}.{}", module_path!(), stringify!(#name))
+            }
+            fn status(&self) -> cdumay_job::Status {
+                self.#status.clone()
+            }
+            fn status_mut(&mut self) -> &mut cdumay_job::Status {
+                &mut self.#status
+            }
+            fn message(&self) -> cdumay_job::Message {
+                self.#message.clone()
+            }
+            fn message_mut(&mut self) -> &mut cdumay_job::Message {
+                &mut self.#message
+            }
+            fn result(&self) -> cdumay_result::Result {
+                self.#result.clone()
+            }
+            fn result_mut(&mut self) -> &mut cdumay_result::Result {
+                &mut self.#result
+            }
+        }
+    })
+}
+
+/// Derives `cdumay_job::TaskInfo`, see the crate documentation for the field attributes.
+#[proc_macro_derive(TaskInfo, attributes(task))]
+pub fn derive_task_info(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_task_info(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
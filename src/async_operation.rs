@@ -0,0 +1,444 @@
+use std::collections::BTreeMap;
+use std::ops::Add;
+use std::sync::Arc;
+
+use crate::errors::TaskCancelled;
+use crate::{AsyncTaskExec, CancellationToken, Message, Status, TaskInfo, TaskSnapshot, TaskStore};
+use async_trait::async_trait;
+use cdumay_error::{Error, Result};
+use log::{debug, error, info, warn};
+use serde_value::Value;
+
+/// Asynchronous counterpart of [`Operation`](crate::Operation), available using the feature `async`.
+///
+/// Tasks are run one after the other, each one using its own retry policy (see
+/// [`AsyncTaskExec::retry_policy`]). Cancellation tokens and stores are supported like in the sync
+/// lifecycle.
+///
+/// Not supported: execution modes (tasks are always sequential), conditions and branches,
+/// compensation, transports (`launch` and `launch_next` use [`AsyncTaskExec::send`]) and restore
+/// from snapshots.
+///
+/// ```rust
+/// use cdumay_job::{define_task, AsyncOperation, AsyncTaskExec, Message, MessageBuilder, Status, TaskInfo};
+///
+/// define_task!(Hello);
+///
+/// #[async_trait::async_trait]
+/// impl AsyncTaskExec for Hello {}
+///
+/// pub struct Greetings {
+///     message: Message,
+///     status: Status,
+///     result: cdumay_result::Result,
+///     tasks: Vec<Hello>,
+/// }
+///
+/// #[async_trait::async_trait]
+/// impl AsyncOperation for Greetings {
+///     type TasksItems = Hello;
+///
+///     fn build_tasks(&self) -> Vec<Hello> {
+///         vec![Hello::new(&MessageBuilder::new("hello".to_string()).build(), None)]
+///     }
+///     fn new(message: &Message, result: Option<cdumay_result::Result>) -> Self {
+///         Greetings {
+///             message: message.clone(),
+///             status: Status::Pending,
+///             result: result.unwrap_or(message.result.clone()),
+///             tasks: vec![],
+///         }
+///     }
+///     fn status(&self) -> Status { self.status.clone() }
+///     fn status_mut(&mut self) -> &mut Status { &mut self.status }
+///     fn message(&self) -> Message { self.message.clone() }
+///     fn message_mut(&mut self) -> &mut Message { &mut self.message }
+///     fn result(&self) -> cdumay_result::Result { self.result.clone() }
+///     fn result_mut(&mut self) -> &mut cdumay_result::Result { &mut self.result }
+///     fn tasks(&self) -> &Vec<Hello> { &self.tasks }
+///     fn tasks_mut(&mut self) -> &mut Vec<Hello> { &mut self.tasks }
+///     fn next(&mut self, _task: &Hello) -> Option<Hello> { None }
+/// }
+///
+/// let message = MessageBuilder::new("greetings".to_string()).build();
+/// let mut operation = Greetings::new(&message, None);
+/// futures::executor::block_on(async {
+///     operation.build().await.unwrap();
+///     operation.execute(None).await;
+/// });
+/// assert_eq!(operation.status(), Status::Success);
+/// assert_eq!(operation.tasks()[0].status(), Status::Success);
+/// ```
+///
+/// The cancellation token is checked between tasks:
+///
+/// ```rust
+/// use cdumay_job::errors::TaskCancelled;
+/// use cdumay_job::{define_task, AsyncOperation, AsyncTaskExec, CancellationToken, Message, MessageBuilder, Status, TaskInfo};
+///
+/// static TOKEN: std::sync::LazyLock<CancellationToken> = std::sync::LazyLock::new(CancellationToken::default);
+///
+/// define_task!(Step);
+///
+/// #[async_trait::async_trait]
+/// impl AsyncTaskExec for Step {
+///     async fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         TOKEN.cancel();
+///         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+///     }
+/// }
+///
+/// pub struct Job {
+///     message: Message,
+///     status: Status,
+///     result: cdumay_result::Result,
+///     tasks: Vec<Step>,
+/// }
+///
+/// #[async_trait::async_trait]
+/// impl AsyncOperation for Job {
+///     type TasksItems = Step;
+///
+///     fn build_tasks(&self) -> Vec<Step> {
+///         let step = |name: &str| Step::new(&MessageBuilder::new(name.to_string()).build(), None);
+///         vec![step("first"), step("second")]
+///     }
+///     fn cancellation_token(&self) -> Option<CancellationToken> {
+///         Some(TOKEN.clone())
+///     }
+///     fn new(message: &Message, result: Option<cdumay_result::Result>) -> Self {
+///         Job {
+///             message: message.clone(),
+///             status: Status::Pending,
+///             result: result.unwrap_or(message.result.clone()),
+///             tasks: vec![],
+///         }
+///     }
+///     fn status(&self) -> Status { self.status.clone() }
+///     fn status_mut(&mut self) -> &mut Status { &mut self.status }
+///     fn message(&self) -> Message { self.message.clone() }
+///     fn message_mut(&mut self) -> &mut Message { &mut self.message }
+///     fn result(&self) -> cdumay_result::Result { self.result.clone() }
+///     fn result_mut(&mut self) -> &mut cdumay_result::Result { &mut self.result }
+///     fn tasks(&self) -> &Vec<Step> { &self.tasks }
+///     fn tasks_mut(&mut self) -> &mut Vec<Step> { &mut self.tasks }
+///     fn next(&mut self, _task: &Step) -> Option<Step> { None }
+/// }
+///
+/// let mut operation = Job::new(&MessageBuilder::new("job".to_string()).build(), None);
+/// let result = futures::executor::block_on(async {
+///     operation.build().await.unwrap();
+///     operation.execute(None).await
+/// });
+/// assert_eq!(operation.status(), Status::Cancelled);
+/// assert_eq!(result.retcode, TaskCancelled::kind.code());
+/// let statuses: Vec<Status> = operation.tasks().iter().map(|task| task.status()).collect();
+/// assert_eq!(statuses, vec![Status::Success, Status::Cancelled]);
+/// ```
+#[async_trait]
+pub trait AsyncOperation: Sized + Send + Sync {
+    type TasksItems: AsyncTaskExec;
+
+    /***********************************************************************************************
+    // Method to check required parameters ( Message.params() <=> Task::required_params() )
+     */
+    async fn check_required_params(&mut self) -> Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Method to format log prefix (=label)
+     */
+    fn label(&self, action: Option<&str>) -> String {
+        format!(
+            "{}[{}]{}",
+            self.message().entrypoint,
+            self.message().uuid,
+            match action {
+                Some(data) => format!(" - {}", data),
+                None => String::new(),
+            }
+        )
+    }
+    /***********************************************************************************************
+    // Post Init - Trigger launched just after initialization, it performs checks
+     */
+    async fn _post_init(&mut self) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self.check_required_params().await?;
+        self.post_init().await
+    }
+    async fn post_init(&mut self) -> Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Pre Run - Trigger launched just before running the task
+     */
+    async fn _pre_run(&mut self) -> Result<cdumay_result::Result> {
+        debug!("{}", self.label(Some("PreRun")));
+        self.pre_run().await
+    }
+    async fn pre_run(&mut self) -> Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Run - Trigger which represent the task body. It usually overwrites
+     */
+    async fn _run(&mut self) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Running).await?;
+        debug!("{}: {}", self.label(Some("Run")), self.result());
+        self.run().await
+    }
+    async fn run(&mut self) -> Result<cdumay_result::Result> {
+        let mut result = self.result();
+        for index in 0..self.tasks().len() {
+            if !matches!(self.tasks()[index].status(), Status::Success | Status::Skipped) {
+                self._check_cancelled()?;
+                result = self.tasks_mut()[index].unsafe_execute(Some(result)).await?;
+            }
+        }
+        Ok(result)
+    }
+    /***********************************************************************************************
+    // Post Run - Trigger launched just after running the task
+     */
+    async fn _post_run(&mut self) -> Result<cdumay_result::Result> {
+        debug!("{}: {}", self.label(Some("PostRun")), self.result());
+        self.post_run().await
+    }
+    async fn post_run(&mut self) -> Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // On Error - Trigger raised if any error is raised
+     */
+    async fn _on_error(&mut self, error: &Error) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Failed).await?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        error!("{}: {}", self.label(Some("Failed")), self.result());
+        let result = self.on_error(error).await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_error(&mut self, error: &Error) -> Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message())
+            .build()
+            .add(&cdumay_result::Result::from(error.clone())))
+    }
+    /***********************************************************************************************
+    // Cancel - Cooperative cancellation, the token is checked between tasks (see
+    // CancellationToken). The tasks which never ran are cancelled and listed in the retval
+    // 'cancelled', the trigger on_cancel is launched instead of on_error
+     */
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        None
+    }
+    fn _check_cancelled(&self) -> Result<()> {
+        match self.cancellation_token() {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+    async fn _on_cancel(&mut self, error: &Error) -> Result<cdumay_result::Result> {
+        let mut cancelled = vec![];
+        for task in self.tasks_mut() {
+            let status = task.status();
+            if matches!(status, Status::Pending | Status::Waiting | Status::Running | Status::Retrying) {
+                task._set_status(Status::Cancelled).await?;
+            }
+            if matches!(status, Status::Pending | Status::Waiting) {
+                cancelled.push(Value::Map(BTreeMap::from([
+                    (Value::String("uuid".to_string()), Value::String(task.message().uuid.to_string())),
+                    (Value::String("entrypoint".to_string()), Value::String(task.message().entrypoint)),
+                ])));
+            }
+        }
+        *self.result_mut() = &self.result() + &self._set_status(Status::Cancelled).await?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        self.result_mut().retval.insert("cancelled".to_string(), Value::Seq(cancelled));
+        warn!("{}: {}", self.label(Some("Cancelled")), self.result());
+        let result = self.on_cancel().await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_cancel(&mut self) -> Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // On Success - Trigger launched if the task has succeeded
+     */
+    async fn _on_success(&mut self) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Success).await?;
+        info!("{}: {}", self.label(Some("Success")), self.result());
+        let result = self.on_success().await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_success(&mut self) -> Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Unsafe Execute - Method to call to get a Result of the task execution. Skipped and cancelled
+    // operations are not run.
+    // NOTE: the trigger on_error is not called!
+     */
+    async fn unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
+        if let Some(data) = result {
+            *self.result_mut() = &self.result() + &data;
+        }
+        if matches!(self.status(), Status::Skipped | Status::Cancelled) {
+            debug!("{}: {}", self.label(Some(&self.status().to_string())), self.result());
+            return Ok(self.result());
+        }
+        *self.result_mut() = &self.result() + &self._post_init().await?;
+        *self.result_mut() = &self.result() + &self._pre_run().await?;
+        *self.result_mut() = &self.result() + &self._run().await?;
+        *self.result_mut() = &self.result() + &self._post_run().await?;
+        self._on_success().await
+    }
+    /***********************************************************************************************
+    // Execute - The method used by the registry
+     */
+    async fn execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_result::Result {
+        match self.unsafe_execute(result).await {
+            Ok(result) => result,
+            Err(err) if err.kind == TaskCancelled::kind => match self._on_cancel(&err).await {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
+            Err(err) => match self._on_error(&err).await {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
+        }
+    }
+    /***********************************************************************************************
+    // Status - Methods to update the status of the task. it can be overwrite to perform action such
+    // as database save ... Illegal transitions (see Status::can_transition_to) raise an error.
+     */
+    async fn _set_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
+        self.status().check_transition_to(&status)?;
+        debug!("{}: status updated '{}' -> '{}'", self.label(Some("SetStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone()).await?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    // Force the status without checking the transition, reserved to admin or recovery tools
+    async fn force_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
+        warn!("{}: status forced '{}' -> '{}'", self.label(Some("ForceStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone()).await?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    async fn set_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
+        *self.status_mut() = status;
+        self.save()?;
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Store - Snapshots are saved by set_status and finalize into the configured store, if any.
+    // They are saved again once the final result is merged, on success, on error and on cancel.
+    // Each status change made by _set_status or force_status is recorded as a transition
+     */
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        None
+    }
+    fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot::new(self.message().entrypoint, self.status(), self.message(), self.result())
+    }
+    fn save(&self) -> Result<()> {
+        match self.store() {
+            Some(store) => store.save(&self.snapshot()),
+            None => Ok(()),
+        }
+    }
+    fn record_transition(&self, from: &Status, to: &Status) -> Result<()> {
+        match self.store() {
+            Some(store) => store.record_transition(&self.message().uuid, from, to),
+            None => Ok(()),
+        }
+    }
+
+    /***********************************************************************************************
+    // On Pre Build - Trigger launched on operation building
+     */
+    async fn _pre_build(&mut self) -> Result<cdumay_result::Result> {
+        debug!("{}: {}", self.label(Some("PreBuild")), self.result());
+        self.pre_build().await
+    }
+    async fn pre_build(&mut self) -> Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Operation build
+     */
+    fn _build_tasks(&self) -> Vec<Self::TasksItems> {
+        self.build_tasks()
+    }
+    fn build_tasks(&self) -> Vec<Self::TasksItems> {
+        vec![]
+    }
+    async fn build(&mut self) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._pre_build().await?;
+        *self.tasks_mut() = self._build_tasks();
+        debug!("{}: {} task(s) found", self.label(Some("Build")), self.tasks().len());
+        self.finalize().await
+    }
+    /***********************************************************************************************
+    // Finalize: Finalize the task, use by operation to perform database save or so one.
+     */
+    async fn finalize(&self) -> Result<cdumay_result::Result> {
+        self.save()?;
+        let mut result = self.result();
+        for task in self.tasks() {
+            result = &result + &task.finalize().await?;
+        }
+        Ok(result)
+    }
+
+    /***********************************************************************************************
+    // Operation over kafka
+     */
+    async fn launch(&mut self, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Waiting).await?;
+        self.launch_next(None, result).await
+    }
+    async fn launch_next(&mut self, task: Option<Self::TasksItems>, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
+        match task {
+            Some(task) => match self.next(&task) {
+                Some(next) => next.send(result).await,
+                None => {
+                    if let Some(result) = result {
+                        *self.result_mut() = &self.result() + &result;
+                    }
+                    // an operation rebuilt from its message on the consuming side is still pending
+                    if self.status() == Status::Pending {
+                        *self.result_mut() = &self.result() + &self._set_status(Status::Waiting).await?;
+                    }
+                    self._set_status(task.status()).await
+                }
+            },
+            None => match self.tasks().first() {
+                Some(first) => first.send(result).await,
+                None => Ok(cdumay_result::ResultBuilder::from(&self.message())
+                    .stderr("Nothing to do, empty operation !".to_string())
+                    .build()),
+            },
+        }
+    }
+
+    // to implement: constructor & property getters / setters
+    fn new(message: &Message, result: Option<cdumay_result::Result>) -> Self;
+    fn status(&self) -> Status;
+    fn status_mut(&mut self) -> &mut Status;
+
+    fn message(&self) -> Message;
+    fn message_mut(&mut self) -> &mut Message;
+    fn result(&self) -> cdumay_result::Result;
+    fn result_mut(&mut self) -> &mut cdumay_result::Result;
+    fn tasks(&self) -> &Vec<Self::TasksItems>;
+    fn tasks_mut(&mut self) -> &mut Vec<Self::TasksItems>;
+
+    // to implement
+    fn next(&mut self, task: &Self::TasksItems) -> Option<Self::TasksItems>;
+}
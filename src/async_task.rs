@@ -0,0 +1,341 @@
+use async_trait::async_trait;
+use log::{debug, error, info, warn};
+use serde_value::Value;
+use std::ops::Add;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::errors::TaskCancelled;
+use crate::{CancellationToken, RetryPolicy, Status, TaskInfo, TaskSnapshot, TaskStore};
+
+/// Asynchronous counterpart of [`TaskExec`](crate::TaskExec), available using the feature `async`.
+///
+/// Hooks are run in the same order and their results are merged the same way. Retry policies,
+/// cancellation tokens and stores are supported like in the sync lifecycle, the delay between two
+/// attempts is awaited using [`AsyncTaskExec::sleep`].
+///
+/// Not supported: timeouts (wrap `execute` into the timeout of the async runtime instead),
+/// transports (`send` doesn't publish anything) and compensation.
+///
+/// ```rust
+/// use cdumay_job::{define_task, AsyncTaskExec, MessageBuilder, Status, TaskInfo};
+///
+/// define_task!(Hello);
+///
+/// #[async_trait::async_trait]
+/// impl AsyncTaskExec for Hello {
+///     async fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message())
+///             .stdout("Hello".to_string())
+///             .build())
+///     }
+/// }
+///
+/// let mut task = Hello::new(&MessageBuilder::new("hello".to_string()).build(), None);
+/// let result = futures::executor::block_on(task.execute(None));
+/// assert_eq!(task.status(), Status::Success);
+/// assert!(result.stdout.unwrap().ends_with("Hello"));
+/// ```
+///
+/// A failed attempt is retried, each status change is saved into the store:
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use cdumay_job::{define_task, AsyncTaskExec, MemoryStore, MessageBuilder, RetryPolicy, Status, TaskInfo, TaskStore};
+///
+/// static CALLS: AtomicUsize = AtomicUsize::new(0);
+/// static STORE: std::sync::LazyLock<Arc<MemoryStore>> = std::sync::LazyLock::new(Default::default);
+///
+/// define_task!(Flaky);
+///
+/// #[async_trait::async_trait]
+/// impl AsyncTaskExec for Flaky {
+///     async fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match CALLS.fetch_add(1, Ordering::SeqCst) {
+///             0 => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+///     fn retry_policy(&self) -> Option<RetryPolicy> {
+///         Some(RetryPolicy::new(3))
+///     }
+///     fn store(&self) -> Option<Arc<dyn TaskStore>> {
+///         Some(STORE.clone())
+///     }
+/// }
+///
+/// let mut task = Flaky::new(&MessageBuilder::new("flaky".to_string()).build(), None);
+/// let result = futures::executor::block_on(task.execute(None));
+/// assert_eq!(task.status(), Status::Success);
+/// assert_eq!(result.retval.get("attempts"), Some(&serde_value::Value::U32(2)));
+/// let snapshot = STORE.load(&task.message().uuid).unwrap().unwrap();
+/// assert_eq!(snapshot.status, Status::Success);
+/// ```
+#[async_trait]
+pub trait AsyncTaskExec: TaskInfo + Sized + Send + Sync + 'static {
+    fn entrypoint() -> String {
+        Self::path()
+    }
+    /***********************************************************************************************
+    // Method to check required parameters ( Message.params() <=> TaskInfo::Params )
+     */
+    async fn check_required_params(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        self.params()?;
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Method to format log prefix (=label)
+     */
+    fn label(&self, action: Option<&str>) -> String {
+        format!(
+            "{}[{}]{}",
+            Self::entrypoint(),
+            self.message().uuid,
+            match action {
+                Some(data) => format!(" - {}", data),
+                None => String::new(),
+            }
+        )
+    }
+    /***********************************************************************************************
+    // Post Init - Trigger launched just after initialization, it perform checks
+     */
+    async fn _post_init(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self.check_required_params().await?;
+        self.post_init().await
+    }
+    async fn post_init(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Pre Run - Trigger launched just before running the task
+     */
+    async fn _pre_run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        debug!("{}", self.label(Some("PreRun")));
+        self.pre_run().await
+    }
+    async fn pre_run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Run - Trigger which represent the task body. It usually overwrites
+     */
+    async fn _run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Running).await?;
+        debug!("{}: {}", self.label(Some("Run")), self.result());
+        self.run().await
+    }
+    async fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Post Run - Trigger launched just after running the task
+     */
+    async fn _post_run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        debug!("{}: {}", self.label(Some("PostRun")), self.result());
+        self.post_run().await
+    }
+    async fn post_run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // On Error - Trigger raised if any error is raised
+     */
+    async fn _on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Failed).await?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        error!("{}: {}", self.label(Some("Failed")), self.result());
+        let result = self.on_error(error).await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message())
+            .build()
+            .add(&cdumay_result::Result::from(error.clone())))
+    }
+    /***********************************************************************************************
+    // On Success - Trigger launched if the task has succeeded
+     */
+    async fn _on_success(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Success).await?;
+        info!("{}: {}", self.label(Some("Success")), self.result());
+        let result = self.on_success().await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_success(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Unsafe Execute - Method to call to get a Result of the task execution. Skipped and cancelled
+    // tasks are not run.
+    // NOTE: the trigger on_error is not called!
+     */
+    async fn unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        if let Some(data) = result {
+            *self.result_mut() = &self.result() + &data;
+        }
+        if matches!(self.status(), Status::Skipped | Status::Cancelled) {
+            debug!("{}: {}", self.label(Some(&self.status().to_string())), self.result());
+            return Ok(self.result());
+        }
+        *self.result_mut() = &self.result() + &self._post_init().await?;
+        let policy = self.retry_policy();
+        let mut attempt = 1;
+        loop {
+            if policy.is_some() {
+                self.result_mut().retval.insert("attempts".to_string(), Value::U32(attempt));
+            }
+            self._check_cancelled()?;
+            match self._attempt().await {
+                Ok(()) => break,
+                Err(err) => match &policy {
+                    Some(policy) if err.kind != TaskCancelled::kind && policy.should_retry(attempt, &err) => {
+                        *self.result_mut() = &self.result() + &self._retry(attempt, &err).await?;
+                        self.sleep(policy.delay(attempt)).await;
+                        attempt += 1;
+                    }
+                    _ => return Err(err),
+                },
+            }
+        }
+        self._on_success().await
+    }
+    async fn _attempt(&mut self) -> cdumay_error::Result<()> {
+        *self.result_mut() = &self.result() + &self._pre_run().await?;
+        *self.result_mut() = &self.result() + &self._run().await?;
+        *self.result_mut() = &self.result() + &self._post_run().await?;
+        Ok(())
+    }
+    /***********************************************************************************************
+    // Retry - Policy used by unsafe_execute to run again pre_run, run and post_run on error, the
+    // trigger on_retry is launched before each new attempt
+     */
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+    async fn _retry(&mut self, attempt: u32, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Retrying).await?;
+        warn!("{}: attempt {} failed: {}", self.label(Some("Retry")), attempt, error);
+        self.on_retry(attempt, error).await
+    }
+    async fn on_retry(&mut self, _attempt: u32, _error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /// Waits the delay between two attempts. The default implementation blocks the current thread,
+    /// override it using the timer of the async runtime (e.g. `tokio::time::sleep`) if the retry
+    /// policy has a backoff.
+    async fn sleep(&self, delay: Duration) {
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+    /***********************************************************************************************
+    // Cancel - Cooperative cancellation, the token is checked before each attempt and can be
+    // checked by run() (see CancellationToken::check). A cancelled task ends with the status
+    // cancelled, the trigger on_cancel is launched instead of on_error
+     */
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        None
+    }
+    fn _check_cancelled(&self) -> cdumay_error::Result<()> {
+        match self.cancellation_token() {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+    async fn _on_cancel(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Cancelled).await?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        warn!("{}: {}", self.label(Some("Cancelled")), self.result());
+        let result = self.on_cancel().await?;
+        self.save()?;
+        Ok(result)
+    }
+    async fn on_cancel(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Execute - The method used by the registry
+     */
+    async fn execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_result::Result {
+        match self.unsafe_execute(result).await {
+            Ok(result) => result,
+            Err(err) if err.kind == TaskCancelled::kind => match self._on_cancel(&err).await {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
+            Err(err) => match self._on_error(&err).await {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
+        }
+    }
+    /***********************************************************************************************
+    // Status - Methods to update the status of the task. it can be overwrite to perform action such
+    // as database save ... Illegal transitions (see Status::can_transition_to) raise an error.
+     */
+    async fn _set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        self.status().check_transition_to(&status)?;
+        debug!("{}: status updated '{}' -> '{}'", self.label(Some("SetStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone()).await?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    // Force the status without checking the transition, reserved to admin or recovery tools
+    async fn force_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        warn!("{}: status forced '{}' -> '{}'", self.label(Some("ForceStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone()).await?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    async fn set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.status_mut() = status;
+        self.save()?;
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Store - Snapshots are saved by set_status and finalize into the configured store, if any.
+    // They are saved again once the final result is merged, on success, on error and on cancel.
+    // Each status change made by _set_status or force_status is recorded as a transition
+     */
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        None
+    }
+    fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot::new(Self::entrypoint(), self.status(), self.message(), self.result())
+    }
+    fn save(&self) -> cdumay_error::Result<()> {
+        match self.store() {
+            Some(store) => store.save(&self.snapshot()),
+            None => Ok(()),
+        }
+    }
+    fn record_transition(&self, from: &Status, to: &Status) -> cdumay_error::Result<()> {
+        match self.store() {
+            Some(store) => store.record_transition(&self.message().uuid, from, to),
+            None => Ok(()),
+        }
+    }
+    /***********************************************************************************************
+    // Send: Send back to kafka (used by operations)
+     */
+    async fn send(&self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        match result {
+            Some(result) => Ok(cdumay_result::ResultBuilder::from(&self.message()).build().add(&result)),
+            None => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+        }
+    }
+    /***********************************************************************************************
+    // Finalize: Finalize the task, use by operation to perform database save or so one.
+     */
+    async fn finalize(&self) -> cdumay_error::Result<cdumay_result::Result> {
+        self.save()?;
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::errors::TaskCancelled;
+
+/// Cooperative cancellation shared by tasks and operations, returned by
+/// [`TaskExec::cancellation_token`](crate::TaskExec::cancellation_token) and
+/// [`Operation::cancellation_token`](crate::Operation::cancellation_token). Clones share the same
+/// state, cancelling one of them cancels them all.
+///
+/// Operations check the token between tasks, long tasks check it from `run()` using
+/// [`CancellationToken::check`]. The tasks which never ran are listed in the retval `cancelled`.
+///
+/// ```rust
+/// use std::sync::LazyLock;
+/// use cdumay_job::errors::TaskCancelled;
+/// use cdumay_job::{define_task, CancellationToken, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// static TOKEN: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::default);
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         // an operator aborts the job while the second step runs
+///         if self.message().entrypoint == "second" {
+///             TOKEN.cancel();
+///         }
+///         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+///     }
+///     fn cancellation_token(&self) -> Option<CancellationToken> {
+///         Some(TOKEN.clone())
+///     }
+/// }
+///
+/// let step = |name: &str| Step::new(&MessageBuilder::new(name.to_string()).build(), None);
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("job".to_string()).build(), None)
+///     .with_tasks(vec![step("first"), step("second"), step("third")])
+///     .with_cancellation_token(TOKEN.clone());
+/// operation.build().unwrap();
+///
+/// let result = operation.execute(None);
+/// assert_eq!(operation.status(), Status::Cancelled);
+/// assert_eq!(result.retcode, TaskCancelled::kind.code());
+/// let statuses: Vec<Status> = operation.tasks().iter().map(|task| task.status()).collect();
+/// assert_eq!(statuses, vec![Status::Success, Status::Success, Status::Cancelled]);
+/// match result.retval.get("cancelled") {
+///     Some(serde_value::Value::Seq(tasks)) => assert_eq!(tasks.len(), 1),
+///     _ => panic!("cancelled tasks not reported"),
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+    /// Returns a [`TaskCancelled`](crate::errors::TaskCancelled) error once cancelled.
+    pub fn check(&self) -> cdumay_error::Result<()> {
+        match self.is_cancelled() {
+            true => Err(TaskCancelled::new().set_message("Cancellation requested".to_string()).into()),
+            false => Ok(()),
+        }
+    }
+}
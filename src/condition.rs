@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde_value::Value;
+
+type ConditionPredicate = Arc<dyn Fn(&cdumay_result::Result, &BTreeMap<String, Value>) -> bool + Send + Sync>;
+
+/// Condition to run a task of an operation, returned by
+/// [`Operation::condition`](crate::Operation::condition). It is evaluated against the result
+/// accumulated by the operation and the metadata of the operation message, a task whose condition
+/// is not met is skipped.
+///
+/// ```rust
+/// use cdumay_job::{define_task, Condition, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+/// use serde_value::Value;
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message())
+///             .retval([("changed".to_string(), Value::Bool(false))].into())
+///             .build())
+///     }
+/// }
+///
+/// let update = Step::new(&MessageBuilder::new("update".to_string()).build(), None);
+/// let cleanup = Step::new(&MessageBuilder::new("cleanup".to_string()).build(), None);
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("deploy".to_string()).build(), None)
+///     .with_condition(&cleanup.message().uuid, Condition::retval_eq("changed", Value::Bool(true)))
+///     .with_tasks(vec![update, cleanup]);
+/// operation.build().unwrap();
+/// operation.execute(None);
+///
+/// assert_eq!(operation.status(), Status::Success);
+/// assert_eq!(operation.tasks()[0].status(), Status::Success);
+/// assert_eq!(operation.tasks()[1].status(), Status::Skipped);
+/// ```
+///
+/// A task which cannot be skipped anymore, like the interrupted task of a restored operation, is
+/// left in its status and is not run again:
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use cdumay_job::{define_task, Condition, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// static ENABLED: AtomicBool = AtomicBool::new(true);
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match self.message().entrypoint.as_str() {
+///             "cleanup" => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+/// }
+///
+/// let message = MessageBuilder::new("deploy".to_string()).build();
+/// let update = Step::new(&MessageBuilder::new("update".to_string()).build(), None);
+/// let cleanup = Step::new(&MessageBuilder::new("cleanup".to_string()).build(), None);
+/// let uuid = cleanup.message().uuid;
+/// let enabled = || Condition::new(|_, _| ENABLED.load(Ordering::SeqCst));
+/// let mut operation = SequentialOperation::new(&message, None)
+///     .with_condition(&uuid, enabled())
+///     .with_tasks(vec![update, cleanup]);
+/// operation.execute(None);
+/// assert_eq!(operation.status(), Status::Failed);
+///
+/// let mut snapshots = vec![operation.snapshot()];
+/// snapshots.extend(operation.tasks().iter().map(|task| task.snapshot()));
+/// ENABLED.store(false, Ordering::SeqCst);
+/// let mut restored = SequentialOperation::<Step>::restore(&message, snapshots).unwrap().with_condition(&uuid, enabled());
+/// restored.execute(None);
+///
+/// assert_eq!(restored.status(), Status::Success);
+/// assert_eq!(restored.tasks()[1].status(), operation.tasks()[1].status());
+/// ```
+#[derive(Clone)]
+pub struct Condition {
+    predicate: ConditionPredicate,
+}
+
+impl Condition {
+    pub fn new<F: Fn(&cdumay_result::Result, &BTreeMap<String, Value>) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+    /// Met when the `retval` of the result contains `key` set to `value`.
+    pub fn retval_eq(key: &str, value: Value) -> Self {
+        let key = key.to_string();
+        Self::new(move |result, _| result.retval.get(&key) == Some(&value))
+    }
+    /// Met when the metadata of the operation message contains `key` set to `value`.
+    pub fn meta_eq(key: &str, value: Value) -> Self {
+        let key = key.to_string();
+        Self::new(move |_, metadata| metadata.get(&key) == Some(&value))
+    }
+    /// Met when this condition is not.
+    pub fn negate(self) -> Self {
+        Self::new(move |result, metadata| !self.evaluate(result, metadata))
+    }
+    pub fn evaluate(&self, result: &cdumay_result::Result, metadata: &BTreeMap<String, Value>) -> bool {
+        (self.predicate)(result, metadata)
+    }
+}
+
+impl fmt::Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Condition").finish_non_exhaustive()
+    }
+}
+
+/// Successors of a task of an operation, returned by
+/// [`Operation::branch`](crate::Operation::branch). The successor is selected using the value of
+/// `key` in the `retval` of the task result, the tasks between the task and its successor are
+/// skipped. Without matching case nor default successor, the operation ends after the task.
+///
+/// ```rust
+/// use cdumay_job::{define_task, Branch, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+/// use serde_value::Value;
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message())
+///             .retval([("target".to_string(), Value::String("staging".to_string()))].into())
+///             .build())
+///     }
+/// }
+///
+/// let step = |name: &str| Step::new(&MessageBuilder::new(name.to_string()).build(), None);
+/// let (check, production, staging, notify) = (step("check"), step("production"), step("staging"), step("notify"));
+/// let branch = Branch::on("target")
+///     .case(Value::String("production".to_string()), production.message().uuid)
+///     .case(Value::String("staging".to_string()), staging.message().uuid);
+///
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("deploy".to_string()).build(), None)
+///     .with_branch(&check.message().uuid, branch)
+///     // once deployed in production, go to the notification
+///     .with_branch(&production.message().uuid, Branch::on("target").otherwise(notify.message().uuid))
+///     .with_tasks(vec![check, production, staging, notify]);
+/// operation.build().unwrap();
+/// operation.execute(None);
+///
+/// let statuses: Vec<Status> = operation.tasks().iter().map(|task| task.status()).collect();
+/// assert_eq!(statuses, vec![Status::Success, Status::Skipped, Status::Success, Status::Success]);
+///
+/// let check = operation.tasks()[0].clone();
+/// assert_eq!(operation.next(&check).unwrap().message().entrypoint, "staging");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Branch {
+    key: String,
+    cases: Vec<(Value, uuid::Uuid)>,
+    otherwise: Option<uuid::Uuid>,
+}
+
+impl Branch {
+    pub fn on(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            cases: vec![],
+            otherwise: None,
+        }
+    }
+    /// Selects the task identified by the uuid of its message when `key` is set to `value`.
+    pub fn case(mut self, value: Value, uuid: uuid::Uuid) -> Self {
+        self.cases.push((value, uuid));
+        self
+    }
+    /// Selects the task identified by the uuid of its message when no case matches.
+    pub fn otherwise(mut self, uuid: uuid::Uuid) -> Self {
+        self.otherwise = Some(uuid);
+        self
+    }
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+    /// Returns the uuid of the selected successor, if any.
+    pub fn select(&self, result: &cdumay_result::Result) -> Option<uuid::Uuid> {
+        let value = result.retval.get(&self.key);
+        self.cases
+            .iter()
+            .find(|(expected, _)| Some(expected) == value)
+            .map(|(_, uuid)| *uuid)
+            .or(self.otherwise)
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+
+use crate::registry::unknown_entrypoint;
+use crate::{CancellationToken, Message, Status, TaskExec, TaskInfo, TaskSnapshot};
+
+/// Object safe counterpart of [`TaskExec`], implemented by every task which is `Clone`. Boxed
+/// tasks implement [`TaskExec`] by delegating to the boxed task, which allows an operation to run
+/// tasks of different types, see [`Registry::register_dyn`](crate::Registry::register_dyn).
+///
+/// ```rust
+/// use cdumay_job::errors::NotBuildable;
+/// use cdumay_job::{define_task, DynTask, MessageBuilder, Operation, Registry, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Download);
+/// impl TaskExec for Download {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message()).stdout("downloaded".to_string()).build())
+///     }
+/// }
+///
+/// define_task!(Install);
+/// impl TaskExec for Install {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message()).stdout("installed".to_string()).build())
+///     }
+/// }
+///
+/// let registry = Registry::default().register_dyn::<Download>().register_dyn::<Install>();
+/// let tasks: Vec<Box<dyn DynTask>> = [Download::entrypoint(), Install::entrypoint()]
+///     .into_iter()
+///     .map(|entrypoint| registry.build(&MessageBuilder::new(entrypoint).build()).unwrap())
+///     .collect();
+///
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("setup".to_string()).build(), None).with_tasks(tasks);
+/// operation.build().unwrap();
+/// let result = operation.execute(None);
+///
+/// assert_eq!(operation.status(), Status::Success);
+/// assert_eq!(operation.tasks()[1].topic(), Install::entrypoint());
+/// assert!(result.stdout.unwrap().ends_with("downloaded\ninstalled"));
+///
+/// // a task registered using register can be executed, not built
+/// let registry = Registry::default().register::<Download>();
+/// match registry.build(&MessageBuilder::new(Download::entrypoint()).build()) {
+///     Err(err) => assert_eq!(err.kind, NotBuildable::kind),
+///     Ok(_) => panic!("Download is not registered using register_dyn"),
+/// }
+/// ```
+pub trait DynTask: Send {
+    fn dyn_clone(&self) -> Box<dyn DynTask>;
+    fn dyn_topic(&self) -> String;
+    fn dyn_status(&self) -> Status;
+    fn dyn_status_mut(&mut self) -> &mut Status;
+    fn dyn_message(&self) -> Message;
+    fn dyn_message_mut(&mut self) -> &mut Message;
+    fn dyn_result(&self) -> cdumay_result::Result;
+    fn dyn_result_mut(&mut self) -> &mut cdumay_result::Result;
+    fn dyn_label(&self, action: Option<&str>) -> String;
+    fn dyn_unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_cancellation_token(&self) -> Option<CancellationToken>;
+    fn dyn_on_cancel(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_force_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_snapshot(&self) -> TaskSnapshot;
+    fn dyn_send(&self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result>;
+    fn dyn_finalize(&self) -> cdumay_error::Result<cdumay_result::Result>;
+}
+
+impl<T: TaskExec + Clone + Send + 'static> DynTask for T {
+    fn dyn_clone(&self) -> Box<dyn DynTask> {
+        Box::new(self.clone())
+    }
+    fn dyn_topic(&self) -> String {
+        self.topic()
+    }
+    fn dyn_status(&self) -> Status {
+        self.status()
+    }
+    fn dyn_status_mut(&mut self) -> &mut Status {
+        self.status_mut()
+    }
+    fn dyn_message(&self) -> Message {
+        self.message()
+    }
+    fn dyn_message_mut(&mut self) -> &mut Message {
+        self.message_mut()
+    }
+    fn dyn_result(&self) -> cdumay_result::Result {
+        self.result()
+    }
+    fn dyn_result_mut(&mut self) -> &mut cdumay_result::Result {
+        self.result_mut()
+    }
+    fn dyn_label(&self, action: Option<&str>) -> String {
+        self.label(action)
+    }
+    fn dyn_unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        self.unsafe_execute(result)
+    }
+    fn dyn_on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        self._on_error(error)
+    }
+    fn dyn_cancellation_token(&self) -> Option<CancellationToken> {
+        self.cancellation_token()
+    }
+    fn dyn_on_cancel(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        self._on_cancel(error)
+    }
+    fn dyn_compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        self._compensate()
+    }
+    fn dyn_set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        self._set_status(status)
+    }
+    fn dyn_force_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        self.force_status(status)
+    }
+    fn dyn_snapshot(&self) -> TaskSnapshot {
+        self.snapshot()
+    }
+    fn dyn_send(&self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        self.send(result)
+    }
+    fn dyn_finalize(&self) -> cdumay_error::Result<cdumay_result::Result> {
+        self.finalize()
+    }
+}
+
+impl Clone for Box<dyn DynTask> {
+    fn clone(&self) -> Self {
+        (**self).dyn_clone()
+    }
+}
+
+impl fmt::Debug for dyn DynTask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynTask")
+            .field("topic", &self.dyn_topic())
+            .field("uuid", &self.dyn_message().uuid)
+            .field("status", &self.dyn_status())
+            .finish_non_exhaustive()
+    }
+}
+
+// NOTE: the boxed task is reached using `**self`, as the box itself implements DynTask
+impl TaskInfo for Box<dyn DynTask> {
+    type Params = serde_value::Value;
+    type Output = serde_value::Value;
+
+    // the type of the task is unknown, the task must be built using Registry::build
+    fn new(msg: &Message, result: Option<cdumay_result::Result>) -> Self {
+        Box::new(UnknownTask::new(msg, result))
+    }
+    fn path() -> String {
+        format!("{}.DynTask", module_path!())
+    }
+    fn status(&self) -> Status {
+        (**self).dyn_status()
+    }
+    fn status_mut(&mut self) -> &mut Status {
+        (**self).dyn_status_mut()
+    }
+    fn message(&self) -> Message {
+        (**self).dyn_message()
+    }
+    fn message_mut(&mut self) -> &mut Message {
+        (**self).dyn_message_mut()
+    }
+    fn result(&self) -> cdumay_result::Result {
+        (**self).dyn_result()
+    }
+    fn result_mut(&mut self) -> &mut cdumay_result::Result {
+        (**self).dyn_result_mut()
+    }
+}
+
+impl TaskExec for Box<dyn DynTask> {
+    fn label(&self, action: Option<&str>) -> String {
+        (**self).dyn_label(action)
+    }
+    fn unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_unsafe_execute(result)
+    }
+    fn _on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_on_error(error)
+    }
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        (**self).dyn_cancellation_token()
+    }
+    fn _on_cancel(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_on_cancel(error)
+    }
+    fn _compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_compensate()
+    }
+    fn _set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_set_status(status)
+    }
+    fn force_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_force_status(status)
+    }
+    fn snapshot(&self) -> TaskSnapshot {
+        (**self).dyn_snapshot()
+    }
+    fn topic(&self) -> String {
+        (**self).dyn_topic()
+    }
+    fn send(&self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_send(result)
+    }
+    fn finalize(&self) -> cdumay_error::Result<cdumay_result::Result> {
+        (**self).dyn_finalize()
+    }
+}
+
+/// Task built by `TaskInfo::new` on a boxed task, which fails as its type is unknown.
+#[derive(Clone, Debug)]
+struct UnknownTask {
+    message: Message,
+    status: Status,
+    result: cdumay_result::Result,
+}
+
+impl TaskInfo for UnknownTask {
+    type Params = serde_value::Value;
+    type Output = serde_value::Value;
+
+    fn new(msg: &Message, result: Option<cdumay_result::Result>) -> Self {
+        UnknownTask {
+            message: msg.clone(),
+            status: Status::Pending,
+            result: result.unwrap_or(msg.result.clone()),
+        }
+    }
+    fn path() -> String {
+        format!("{}.UnknownTask", module_path!())
+    }
+    fn status(&self) -> Status {
+        self.status.clone()
+    }
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
+    }
+    fn message(&self) -> Message {
+        self.message.clone()
+    }
+    fn message_mut(&mut self) -> &mut Message {
+        &mut self.message
+    }
+    fn result(&self) -> cdumay_result::Result {
+        self.result.clone()
+    }
+    fn result_mut(&mut self) -> &mut cdumay_result::Result {
+        &mut self.result
+    }
+}
+
+impl TaskExec for UnknownTask {
+    fn topic(&self) -> String {
+        self.message.entrypoint.clone()
+    }
+    fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Err(unknown_entrypoint(&self.message))
+    }
+}
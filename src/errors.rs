@@ -0,0 +1,30 @@
+//! Errors raised by the job lifecycle, each one has a dedicated `cdumay_error::ErrorKind`.
+use cdumay_error::{define_errors, define_kinds, AsError};
+
+define_kinds! {
+    ValidationError = ("JOB-00001", 400, "Validation error"),
+    TransitionError = ("JOB-00002", 409, "Invalid status transition"),
+    TimeoutError = ("JOB-00003", 504, "Task timed out"),
+    ExecutionError = ("JOB-00004", 500, "Task execution failed"),
+    NotFoundError = ("JOB-00005", 404, "Not found"),
+    TransportError = ("JOB-00006", 503, "Transport unavailable"),
+    StoreError = ("JOB-00007", 500, "Task store error"),
+    CancellationError = ("JOB-00008", 499, "Cancelled"),
+}
+
+define_errors! {
+    InvalidStatus = ValidationError,
+    InvalidTransition = TransitionError,
+    TaskTimeout = TimeoutError,
+    TaskPanicked = ExecutionError,
+    UnknownEntrypoint = NotFoundError,
+    ChannelClosed = TransportError,
+    TransportFailure = TransportError,
+    StoreFailure = StoreError,
+    TasksFailed = ExecutionError,
+    InvalidWorkflow = ValidationError,
+    TaskCancelled = CancellationError,
+    InvalidParams = ValidationError,
+    InvalidOutput = ValidationError,
+    NotBuildable = ValidationError,
+}
@@ -42,6 +42,9 @@
 //! }
 //!
 //! impl TaskInfo for Hello {
+//!     type Params = Params;
+//!     type Output = Value;
+//!
 //!     fn new(msg: &Message, result: Option<Result>) -> Hello {
 //!         Hello {
 //!             message: msg.clone(),
@@ -69,10 +72,8 @@
 //!             Err(_) => "localhost".to_string()
 //!         };
 //!
-//!         let params: Params = match self.message().params {
-//!             Some(params) => params.deserialize_into().unwrap(),
-//!             None => Params {user: "undef".to_string()}
-//!         };
+//!         // params are checked before running the task, see TaskExec::check_required_params
+//!         let params = self.params()?;
 //!         Ok(ResultBuilder::from(&self.message())
 //!             .stdout(format!("Hello {} from {}", params.user, host))
 //!             .build()
@@ -130,7 +131,7 @@
 //!     user: String
 //! }
 //!
-//! define_task!(Hello);
+//! define_task!(Hello, Params);
 //!
 //! impl TaskExec for Hello {
 //!     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
@@ -140,10 +141,8 @@
 //!             Err(_) => "localhost".to_string()
 //!         };
 //!
-//!         let params: Params = match self.message().params {
-//!             Some(params) => params.deserialize_into().unwrap(),
-//!             None => Params {user: "undef".to_string()}
-//!         };
+//!         // params are checked before running the task, see TaskExec::check_required_params
+//!         let params = self.params()?;
 //!         Ok(ResultBuilder::from(&self.message())
 //!             .stdout(format!("Hello {} from {}", params.user, host))
 //!             .build()
@@ -165,14 +164,96 @@
 //! }
 //! ```
 //!
+//! Operations can be generated the same way using `define_operation!`, the optional block is
+//! inserted into the `Operation` implementation (`next` is generated unless the block defines it):
+//!
+//! ```rust
+//! use cdumay_job::{define_operation, define_task, MessageBuilder, Operation, TaskExec, TaskInfo};
+//! use cdumay_result::ResultBuilder;
+//!
+//! define_task!(Hello);
+//!
+//! impl TaskExec for Hello {
+//!     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+//!         Ok(ResultBuilder::from(&self.message())
+//!             .stdout(format!("Hello from {}", self.message().entrypoint))
+//!             .build()
+//!         )
+//!     }
+//! }
+//!
+//! define_operation!(Greetings, Hello, {
+//!     fn build_tasks(&self) -> Vec<Hello> {
+//!         ["hello.world", "hello.moon"]
+//!             .iter()
+//!             .map(|entrypoint| Hello::new(&MessageBuilder::new(entrypoint.to_string()).build(), None))
+//!             .collect()
+//!     }
+//! });
+//!
+//! fn main() {
+//!     env_logger::init();
+//!     let message = MessageBuilder::new("greetings".to_string()).build();
+//!
+//!     let mut operation = Greetings::new(&message, None);
+//!     operation.build().unwrap();
+//!     println!("{}", serde_json::to_string_pretty(&operation.execute(None)).unwrap());
+//! }
+//! ```
+//!
+//! Tasks which need more than a message, a status and a result can derive `TaskInfo` instead, using
+//! the feature `derive`. See [cdumay_job_derive](https://docs.rs/cdumay_job_derive) documentation for more information.
+//!
+
+#![allow(clippy::result_large_err)]
 
+#[cfg(feature = "async")]
+pub use async_operation::AsyncOperation;
+#[cfg(feature = "async")]
+pub use async_task::AsyncTaskExec;
+pub use cancellation::CancellationToken;
+pub use condition::{Branch, Condition};
+pub use dyn_task::DynTask;
 pub use messages::{Message, MessageBuilder};
+pub use operation::{ExecutionMode, FailurePolicy, Operation, SequentialOperation};
+pub use registry::Registry;
+pub use retry::{Backoff, RetryPolicy};
+// default params and output of the tasks, also used by define_task! and the derive macro
+pub use serde_value::Value;
 pub use status::Status;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteStore, StatusTransition, TaskHistory};
+pub use store::{FileStore, MemoryStore, TaskSnapshot, TaskStore};
 pub use task::{TaskExec, TaskInfo};
+pub use timeout::Timeout;
+pub use transport::{MemoryTransport, SpoolTransport, Transport};
+pub use worker::{ChannelSink, ChannelSource, MessageSource, ResultSink, Worker};
+pub use workflow::Workflow;
 
+#[cfg(feature = "async")]
+mod async_operation;
+#[cfg(feature = "async")]
+mod async_task;
+mod cancellation;
+mod condition;
+mod dyn_task;
+pub mod errors;
 mod messages;
 mod operation;
+mod params;
+mod registry;
+mod retry;
 mod status;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
 mod task;
+mod timeout;
+mod transport;
+mod worker;
+mod workflow;
 #[macro_use]
 mod macros;
+
+#[cfg(feature = "derive")]
+pub use cdumay_job_derive::*;
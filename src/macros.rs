@@ -1,34 +1,43 @@
 #[macro_export]
 macro_rules! define_task {
     ($name:ident) => {
+        $crate::define_task!($name, $crate::Value);
+    };
+    ($name:ident, $params:ty) => {
+        $crate::define_task!($name, $params, $crate::Value);
+    };
+    ($name:ident, $params:ty, $output:ty) => {
         #[derive(Clone, Debug)]
         pub struct $name {
-            message: cdumay_job::Message,
-            status: cdumay_job::Status,
+            message: $crate::Message,
+            status: $crate::Status,
             result: cdumay_result::Result,
         }
 
-        impl cdumay_job::TaskInfo for $name {
-            fn new(msg: &cdumay_job::Message, result: Option<cdumay_result::Result>) -> $name {
+        impl $crate::TaskInfo for $name {
+            type Params = $params;
+            type Output = $output;
+
+            fn new(msg: &$crate::Message, result: Option<cdumay_result::Result>) -> $name {
                 $name {
                     message: msg.clone(),
-                    status: cdumay_job::Status::Pending,
+                    status: $crate::Status::Pending,
                     result: result.unwrap_or(msg.result.clone()),
                 }
             }
             fn path() -> String {
                 format!("{}.{}", module_path!(), stringify!($name))
             }
-            fn status(&self) -> cdumay_job::Status {
+            fn status(&self) -> $crate::Status {
                 self.status.clone()
             }
-            fn status_mut(&mut self) -> &mut cdumay_job::Status {
+            fn status_mut(&mut self) -> &mut $crate::Status {
                 &mut self.status
             }
-            fn message(&self) -> cdumay_job::Message {
+            fn message(&self) -> $crate::Message {
                 self.message.clone()
             }
-            fn message_mut(&mut self) -> &mut cdumay_job::Message {
+            fn message_mut(&mut self) -> &mut $crate::Message {
                 &mut self.message
             }
             fn result(&self) -> cdumay_result::Result {
@@ -39,12 +48,155 @@ macro_rules! define_task {
             }
         }
 
-        impl From<&cdumay_job::Message> for $name {
-            fn from(msg: &cdumay_job::Message) -> $name {
+        impl From<&$crate::Message> for $name {
+            fn from(msg: &$crate::Message) -> $name {
+                $name {
+                    message: msg.clone(),
+                    status: $crate::Status::Pending,
+                    result: msg.result.clone(),
+                }
+            }
+        }
+    };
+}
+
+/// Defines an operation running tasks of type `$task`. The optional block is inserted into the
+/// [`Operation`](crate::Operation) implementation, `next` is generated unless the block defines it:
+///
+/// ```rust
+/// use cdumay_job::{define_operation, define_task, MessageBuilder, Operation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {}
+///
+/// define_operation!(Greetings, Hello, {
+///     fn build_tasks(&self) -> Vec<Hello> {
+///         vec![Hello::new(&MessageBuilder::new("hello".to_string()).build(), None)]
+///     }
+///     fn next(&mut self, _task: &Hello) -> Option<Hello> {
+///         None
+///     }
+/// });
+///
+/// let mut operation = Greetings::new(&MessageBuilder::new("greetings".to_string()).build(), None);
+/// operation.build().unwrap();
+/// operation.execute(None);
+/// assert_eq!(operation.status(), Status::Success);
+/// let task = operation.tasks()[0].clone();
+/// assert!(operation.next(&task).is_none());
+/// ```
+///
+/// Without a `next` method in the block, `next` returns the task following the given one:
+///
+/// ```rust
+/// use cdumay_job::{define_operation, define_task, MessageBuilder, Operation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Step);
+/// impl TaskExec for Step {}
+///
+/// define_operation!(Deploy, Step, {
+///     fn build_tasks(&self) -> Vec<Step> {
+///         ["build", "test", "release"]
+///             .into_iter()
+///             .map(|name| Step::new(&MessageBuilder::new(name.to_string()).build(), None))
+///             .collect()
+///     }
+/// });
+///
+/// let mut operation = Deploy::new(&MessageBuilder::new("deploy".to_string()).build(), None);
+/// operation.build().unwrap();
+/// let tasks = operation.tasks().clone();
+/// assert_eq!(operation.next(&tasks[0]).map(|task| task.message().entrypoint), Some("test".to_string()));
+/// assert_eq!(operation.next(&tasks[1]).map(|task| task.message().entrypoint), Some("release".to_string()));
+/// assert!(operation.next(&tasks[2]).is_none());
+///
+/// operation.execute(None);
+/// assert_eq!(operation.status(), Status::Success);
+/// assert!(operation.tasks().iter().all(|task| task.status() == Status::Success));
+/// ```
+#[macro_export]
+macro_rules! define_operation {
+    ($name:ident, $task:ty) => {
+        $crate::define_operation!($name, $task, {});
+    };
+    ($name:ident, $task:ty, { $($body:tt)* }) => {
+        $crate::define_operation!(@next $name, $task, { $($body)* } $($body)*);
+    };
+    // the block is scanned for a `next` method, one method (or token) at a time
+    (@next $name:ident, $task:ty, { $($body:tt)* } fn next $($rest:tt)*) => {
+        $crate::define_operation!(@impl $name, $task, { $($body)* });
+    };
+    (@next $name:ident, $task:ty, { $($body:tt)* } fn $method:ident ( $($args:tt)* ) $(-> $output:ty)? { $($block:tt)* } $($rest:tt)*) => {
+        $crate::define_operation!(@next $name, $task, { $($body)* } $($rest)*);
+    };
+    (@next $name:ident, $task:ty, { $($body:tt)* } $token:tt $($rest:tt)*) => {
+        $crate::define_operation!(@next $name, $task, { $($body)* } $($rest)*);
+    };
+    (@next $name:ident, $task:ty, { $($body:tt)* }) => {
+        $crate::define_operation!(@impl $name, $task, {
+            $($body)*
+
+            fn next(&mut self, task: &$task) -> Option<$task> {
+                let position = $crate::Operation::_next_index(self, task)?;
+                self.tasks.get(position).cloned()
+            }
+        });
+    };
+    (@impl $name:ident, $task:ty, { $($body:tt)* }) => {
+        #[derive(Clone, Debug)]
+        pub struct $name {
+            message: $crate::Message,
+            status: $crate::Status,
+            result: cdumay_result::Result,
+            tasks: Vec<$task>,
+        }
+
+        impl $crate::Operation for $name {
+            type TasksItems = $task;
+
+            $($body)*
+
+            fn new(msg: &$crate::Message, result: Option<cdumay_result::Result>) -> $name {
+                $name {
+                    message: msg.clone(),
+                    status: $crate::Status::Pending,
+                    result: result.unwrap_or(msg.result.clone()),
+                    tasks: vec![],
+                }
+            }
+            fn status(&self) -> $crate::Status {
+                self.status.clone()
+            }
+            fn status_mut(&mut self) -> &mut $crate::Status {
+                &mut self.status
+            }
+            fn message(&self) -> $crate::Message {
+                self.message.clone()
+            }
+            fn message_mut(&mut self) -> &mut $crate::Message {
+                &mut self.message
+            }
+            fn result(&self) -> cdumay_result::Result {
+                self.result.clone()
+            }
+            fn result_mut(&mut self) -> &mut cdumay_result::Result {
+                &mut self.result
+            }
+            fn tasks(&self) -> &Vec<$task> {
+                &self.tasks
+            }
+            fn tasks_mut(&mut self) -> &mut Vec<$task> {
+                &mut self.tasks
+            }
+        }
+
+        impl From<&$crate::Message> for $name {
+            fn from(msg: &$crate::Message) -> $name {
                 $name {
                     message: msg.clone(),
-                    status: cdumay_job::Status::Pending,
+                    status: $crate::Status::Pending,
                     result: msg.result.clone(),
+                    tasks: vec![],
                 }
             }
         }
@@ -75,6 +75,6 @@ impl Default for Message {
 
 impl From<&Message> for ResultBuilder {
     fn from(msg: &Message) -> ResultBuilder {
-        ResultBuilder::default().uuid(msg.uuid.clone())
+        ResultBuilder::default().uuid(msg.uuid)
     }
 }
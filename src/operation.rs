@@ -1,11 +1,173 @@
+use std::collections::BTreeMap;
 use std::ops::Add;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
-use crate::{Message, Status, TaskExec, TaskInfo};
+use crate::errors::{TaskCancelled, TaskPanicked, TasksFailed};
+use crate::transport::publish_result;
+use crate::{Branch, CancellationToken, Condition, Message, Status, TaskExec, TaskInfo, TaskSnapshot, TaskStore, Transport};
 use cdumay_error::{Error, Result};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use serde_value::Value;
+
+/// How an [`Operation`] runs its tasks.
+///
+/// ```rust
+/// use cdumay_job::{define_operation, define_task, ExecutionMode, FailurePolicy, MessageBuilder, Operation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Ping);
+///
+/// impl TaskExec for Ping {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match self.message().entrypoint.starts_with("down") {
+///             true => Err(cdumay_error::Error::default()),
+///             false => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+/// }
+///
+/// define_operation!(PingAll, Ping, {
+///     fn execution_mode(&self) -> ExecutionMode {
+///         ExecutionMode::Parallel { concurrency: 2, on_failure: FailurePolicy::CollectAll }
+///     }
+///     fn build_tasks(&self) -> Vec<Ping> {
+///         ["host1", "down1", "host2", "down2"]
+///             .iter()
+///             .map(|host| Ping::new(&MessageBuilder::new(host.to_string()).build(), None))
+///             .collect()
+///     }
+/// });
+///
+/// let mut operation = PingAll::new(&MessageBuilder::new("ping".to_string()).build(), None);
+/// operation.build().unwrap();
+/// let result = operation.execute(None);
+///
+/// assert_eq!(operation.status(), Status::Failed);
+/// assert_eq!(operation.tasks().iter().filter(|task| task.status() == Status::Success).count(), 2);
+/// match result.retval.get("errors") {
+///     Some(serde_value::Value::Seq(errors)) => assert_eq!(errors.len(), 2),
+///     _ => panic!("errors not reported"),
+/// }
+/// ```
+///
+/// A task which panics fails with a [`TaskPanicked`](crate::errors::TaskPanicked) error, the other
+/// tasks are still run:
+///
+/// ```rust
+/// use cdumay_job::errors::TaskPanicked;
+/// use cdumay_job::{define_operation, define_task, ExecutionMode, FailurePolicy, MessageBuilder, Operation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Ping);
+///
+/// impl TaskExec for Ping {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match self.message().entrypoint.as_str() {
+///             "crash" => panic!("connection reset"),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+/// }
+///
+/// define_operation!(PingAll, Ping, {
+///     fn execution_mode(&self) -> ExecutionMode {
+///         ExecutionMode::Parallel { concurrency: 2, on_failure: FailurePolicy::CollectAll }
+///     }
+///     fn build_tasks(&self) -> Vec<Ping> {
+///         ["crash", "host1"]
+///             .iter()
+///             .map(|host| Ping::new(&MessageBuilder::new(host.to_string()).build(), None))
+///             .collect()
+///     }
+/// });
+///
+/// let mut operation = PingAll::new(&MessageBuilder::new("ping".to_string()).build(), None);
+/// operation.build().unwrap();
+/// let result = operation.execute(None);
+///
+/// assert_eq!(operation.status(), Status::Failed);
+/// assert_eq!(operation.tasks()[1].status(), Status::Success);
+/// match result.retval.get("errors") {
+///     Some(serde_value::Value::Seq(errors)) => match &errors[0] {
+///         serde_value::Value::Map(error) => {
+///             let code = error.get(&serde_value::Value::String("code".to_string()));
+///             assert_eq!(code, Some(&serde_value::Value::U16(TaskPanicked::kind.code())));
+///         }
+///         _ => panic!("error not reported"),
+///     },
+///     _ => panic!("errors not reported"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionMode {
+    /// Tasks run one after the other, each one receiving the result of the previous one.
+    Sequential,
+    /// Tasks run on `concurrency` threads.
+    Parallel { concurrency: usize, on_failure: FailurePolicy },
+}
+
+/// What happens to the remaining tasks of a parallel operation when a task fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailurePolicy {
+    /// No more task is started, the first error is returned.
+    FailFast,
+    /// Every task is run, errors are reported together.
+    CollectAll,
+}
+
+// Reports the errors of several tasks as a single error
+pub(crate) fn tasks_failed(failures: Vec<(Message, Error)>) -> Error {
+    TasksFailed::new()
+        .set_message(format!("{} task(s) failed", failures.len()))
+        .set_details(BTreeMap::from([(
+            "errors".to_string(),
+            Value::Seq(
+                failures
+                    .into_iter()
+                    .map(|(message, err)| {
+                        Value::Map(BTreeMap::from([
+                            (Value::String("uuid".to_string()), Value::String(message.uuid.to_string())),
+                            (Value::String("entrypoint".to_string()), Value::String(message.entrypoint)),
+                            (Value::String("code".to_string()), Value::U16(err.kind.code())),
+                            (Value::String("class".to_string()), Value::String(err.class)),
+                            (Value::String("message".to_string()), Value::String(err.message)),
+                        ]))
+                    })
+                    .collect(),
+            ),
+        )]))
+        .into()
+}
+
+// Ends a run whose tasks failed or were not all run: successful tasks are kept in the result of the
+// operation, on_error completes it
+pub(crate) fn end_run<O: Operation + ?Sized>(
+    operation: &mut O,
+    result: cdumay_result::Result,
+    mut errors: Vec<(usize, Error)>,
+    complete: bool,
+    on_failure: FailurePolicy,
+) -> Result<cdumay_result::Result> {
+    if errors.is_empty() && complete {
+        return Ok(result);
+    }
+    *operation.result_mut() = result;
+    match on_failure {
+        _ if errors.is_empty() => operation._check_cancelled().map(|_| operation.result()),
+        FailurePolicy::FailFast => Err(errors.swap_remove(0).1),
+        FailurePolicy::CollectAll => Err(tasks_failed(
+            errors
+                .into_iter()
+                .map(|(index, err)| (operation.tasks()[index].message(), err))
+                .collect(),
+        )),
+    }
+}
 
 pub trait Operation {
-    type TasksItems: TaskExec;
+    // tasks are sent to threads by parallel runs
+    type TasksItems: TaskExec + Send;
 
     /***********************************************************************************************
     // Method to check required parameters ( Message.params() <=> Task::required_params() )
@@ -56,14 +218,142 @@ pub trait Operation {
         self.run()
     }
     fn run(&mut self) -> Result<cdumay_result::Result> {
+        match self.execution_mode() {
+            ExecutionMode::Sequential => self._run_sequential(),
+            ExecutionMode::Parallel { concurrency, on_failure } => self._run_parallel(concurrency, on_failure),
+        }
+    }
+    /***********************************************************************************************
+    // Execution mode - Sequential tasks receive the result of the previous one, parallel tasks are
+    // independent: they all receive the result of the operation
+     */
+    fn execution_mode(&self) -> ExecutionMode {
+        ExecutionMode::Sequential
+    }
+    fn _run_sequential(&mut self) -> Result<cdumay_result::Result> {
         let mut result = self.result();
-        for task in self.tasks_mut() {
-            if task.status() != Status::Success {
-                result = task.unsafe_execute(Some(result))?;
+        // position of the task selected by the last branch, the tasks before are skipped
+        let mut selected = 0;
+        for index in 0..self.tasks().len() {
+            match self.tasks()[index].status() {
+                Status::Skipped => continue,
+                Status::Success => {}
+                _ if index < selected => {
+                    debug!("{}: not selected, skip {}", self.label(Some("Branch")), self.tasks()[index].label(None));
+                    if self.tasks()[index].status().can_transition_to(&Status::Skipped) {
+                        self.tasks_mut()[index]._set_status(Status::Skipped)?;
+                    }
+                    continue;
+                }
+                _ if self._skip_unmet(index, &result)? => continue,
+                _ => {
+                    self._check_cancelled()?;
+                    result = self.tasks_mut()[index].unsafe_execute(Some(result))?;
+                }
+            }
+            if self.branch(&self.tasks()[index]).is_some() {
+                selected = self._next_index(&self.tasks()[index]).unwrap_or(self.tasks().len());
             }
         }
         Ok(result)
     }
+    fn _run_parallel(&mut self, concurrency: usize, on_failure: FailurePolicy) -> Result<cdumay_result::Result> {
+        debug!("{}: {} thread(s), {:?}", self.label(Some("Parallel")), concurrency.max(1), on_failure);
+        let input = self.result();
+        for index in 0..self.tasks().len() {
+            if !matches!(self.tasks()[index].status(), Status::Success | Status::Skipped) {
+                self._skip_unmet(index, &input)?;
+            }
+        }
+        let failed = AtomicBool::new(false);
+        let token = self.cancellation_token();
+        let outcomes = Mutex::new(vec![]);
+        let pending = Mutex::new(
+            self.tasks_mut()
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, task)| !matches!(task.status(), Status::Success | Status::Skipped)),
+        );
+        thread::scope(|scope| {
+            for _ in 0..concurrency.max(1) {
+                scope.spawn(|| {
+                    while (on_failure == FailurePolicy::CollectAll || !failed.load(Ordering::SeqCst))
+                        && !token.as_ref().is_some_and(CancellationToken::is_cancelled)
+                    {
+                        let Some((index, task)) = pending.lock().ok().and_then(|mut tasks| tasks.next()) else {
+                            break;
+                        };
+                        let outcome = panic::catch_unwind(AssertUnwindSafe(|| task.unsafe_execute(Some(input.clone()))))
+                            .unwrap_or_else(|_| {
+                                Err(TaskPanicked::new()
+                                    .set_message("Task panicked during run".to_string())
+                                    .into())
+                            });
+                        if outcome.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        if let Ok(mut outcomes) = outcomes.lock() {
+                            outcomes.push((index, outcome));
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut result = self.result();
+        let mut errors = vec![];
+        let mut outcomes = outcomes.into_inner().unwrap_or_default();
+        outcomes.sort_by_key(|(index, _)| *index);
+        for (index, outcome) in outcomes {
+            match outcome {
+                Ok(data) => result = &result + &data,
+                Err(err) => errors.push((index, err)),
+            }
+        }
+        let interrupted = self.tasks().iter().any(|task| matches!(task.status(), Status::Pending | Status::Waiting));
+        end_run(self, result, errors, !interrupted, on_failure)
+    }
+    /***********************************************************************************************
+    // Condition - A task whose condition is not met is skipped instead of run. Conditions are
+    // evaluated against the accumulated result and the metadata of the operation message (see
+    // Condition)
+     */
+    fn condition(&self, _task: &Self::TasksItems) -> Option<Condition> {
+        None
+    }
+    fn _skip_unmet(&mut self, index: usize, result: &cdumay_result::Result) -> Result<bool> {
+        let task = &self.tasks()[index];
+        match self.condition(task) {
+            Some(condition) if !condition.evaluate(result, &self.message().metadata) => {
+                debug!("{}: condition not met, skip {}", self.label(Some("Condition")), task.label(None));
+                // a task restored in a final status is left as is, it is not run either
+                if task.status().can_transition_to(&Status::Skipped) {
+                    self.tasks_mut()[index]._set_status(Status::Skipped)?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    /***********************************************************************************************
+    // Branch - The successor of a task having a branch is selected using the task result (see
+    // Branch), the tasks in between are skipped. Branches are ignored by parallel operations
+     */
+    fn branch(&self, _task: &Self::TasksItems) -> Option<Branch> {
+        None
+    }
+    // Position of the successor of the task, to be used by next()
+    fn _next_index(&self, task: &Self::TasksItems) -> Option<usize> {
+        let uuid = match self.branch(task) {
+            Some(branch) => branch.select(&task.result())?,
+            None => task.message().uuid,
+        };
+        let position = self.tasks().iter().position(|item| item.message().uuid == uuid)?;
+        match self.branch(task) {
+            Some(_) => Some(position),
+            None => Some(position + 1).filter(|next| *next < self.tasks().len()),
+        }
+    }
     /***********************************************************************************************
     // Post Run - Trigger launched just after running the task
      */
@@ -81,7 +371,15 @@ pub trait Operation {
         *self.result_mut() = &self.result() + &self._set_status(Status::Failed)?;
         *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
         error!("{}: {}", self.label(Some("Failed")), self.result());
-        self.on_error(error)
+        let result = match self.compensate_on_error() {
+            true => {
+                let compensations = self._compensate()?;
+                &self.on_error(error)? + &compensations
+            }
+            false => self.on_error(error)?,
+        };
+        self.save()?;
+        Ok(result)
     }
     fn on_error(&mut self, error: &Error) -> Result<cdumay_result::Result> {
         Ok(cdumay_result::ResultBuilder::from(&self.message())
@@ -89,24 +387,113 @@ pub trait Operation {
             .add(&cdumay_result::Result::from(error.clone())))
     }
     /***********************************************************************************************
+    // Cancel - Cooperative cancellation, the token is checked between tasks (see
+    // CancellationToken). The tasks which never ran are cancelled and listed in the retval
+    // 'cancelled', the trigger on_cancel is launched instead of on_error
+     */
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        None
+    }
+    fn _check_cancelled(&self) -> Result<()> {
+        match self.cancellation_token() {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+    fn _on_cancel(&mut self, error: &Error) -> Result<cdumay_result::Result> {
+        let mut cancelled = vec![];
+        for task in self.tasks_mut() {
+            let status = task.status();
+            if matches!(status, Status::Pending | Status::Waiting | Status::Running | Status::Retrying) {
+                task._set_status(Status::Cancelled)?;
+            }
+            if matches!(status, Status::Pending | Status::Waiting) {
+                cancelled.push(Value::Map(BTreeMap::from([
+                    (Value::String("uuid".to_string()), Value::String(task.message().uuid.to_string())),
+                    (Value::String("entrypoint".to_string()), Value::String(task.message().entrypoint)),
+                ])));
+            }
+        }
+        *self.result_mut() = &self.result() + &self._set_status(Status::Cancelled)?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        self.result_mut().retval.insert("cancelled".to_string(), Value::Seq(cancelled));
+        warn!("{}: {}", self.label(Some("Cancelled")), self.result());
+        let result = self.on_cancel()?;
+        self.save()?;
+        Ok(result)
+    }
+    fn on_cancel(&mut self) -> Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Compensate - On error, succeeded tasks are undone in reverse order (see
+    // TaskExec::compensate). The operation ends compensated, or compensation failed if any task
+    // could not be undone. The outcome of each compensation is listed in the retval
+    // 'compensations'
+     */
+    fn compensate_on_error(&self) -> bool {
+        false
+    }
+    fn _compensate(&mut self) -> Result<cdumay_result::Result> {
+        let mut result = cdumay_result::ResultBuilder::from(&self.message()).build();
+        let mut compensations = vec![];
+        let mut failed = false;
+        for index in (0..self.tasks().len()).rev() {
+            if self.tasks()[index].status() != Status::Success {
+                continue;
+            }
+            let task = &mut self.tasks_mut()[index];
+            let outcome = match task._compensate() {
+                Ok(data) => data,
+                Err(err) => {
+                    failed = true;
+                    cdumay_result::Result::from(err)
+                }
+            };
+            result = &result + &outcome;
+            compensations.push(Value::Map(BTreeMap::from([
+                (Value::String("uuid".to_string()), Value::String(task.message().uuid.to_string())),
+                (Value::String("entrypoint".to_string()), Value::String(task.message().entrypoint)),
+                (Value::String("status".to_string()), Value::from(task.status())),
+                (Value::String("retcode".to_string()), Value::U16(outcome.retcode)),
+            ])));
+        }
+        debug!("{}: {} task(s) compensated", self.label(Some("Compensate")), compensations.len());
+        result.retval.insert("compensations".to_string(), Value::Seq(compensations));
+        let status = match failed {
+            true => Status::CompensationFailed,
+            false => Status::Compensated,
+        };
+        result = &result + &self._set_status(status)?;
+        *self.result_mut() = &self.result() + &result;
+        Ok(result)
+    }
+    /***********************************************************************************************
     // On Success - Trigger launched if the task has succeeded
      */
     fn _on_success(&mut self) -> Result<cdumay_result::Result> {
         *self.result_mut() = &self.result() + &self._set_status(Status::Success)?;
         info!("{}: {}", self.label(Some("Success")), self.result());
-        self.on_success()
+        let result = self.on_success()?;
+        self.save()?;
+        Ok(result)
     }
     fn on_success(&mut self) -> Result<cdumay_result::Result> {
-        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+        Ok(self.result())
     }
     /***********************************************************************************************
-    // Unsafe Execute - Method to call to get a Result of the task execution.
+    // Unsafe Execute - Method to call to get a Result of the task execution. Skipped and cancelled
+    // operations are not run.
     // NOTE: the trigger on_error is not called!
      */
     fn unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
         if let Some(data) = result {
             *self.result_mut() = &self.result() + &data;
         }
+        if matches!(self.status(), Status::Skipped | Status::Cancelled) {
+            debug!("{}: {}", self.label(Some(&self.status().to_string())), self.result());
+            return Ok(self.result());
+        }
         *self.result_mut() = &self.result() + &self._post_init()?;
         *self.result_mut() = &self.result() + &self._pre_run()?;
         *self.result_mut() = &self.result() + &self._run()?;
@@ -119,6 +506,10 @@ pub trait Operation {
     fn execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_result::Result {
         match self.unsafe_execute(result) {
             Ok(result) => result,
+            Err(err) if err.kind == TaskCancelled::kind => match self._on_cancel(&err) {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
             Err(err) => match self._on_error(&err) {
                 Ok(result) => result,
                 Err(err) => cdumay_result::Result::from(err),
@@ -127,16 +518,52 @@ pub trait Operation {
     }
     /***********************************************************************************************
     // Status - Methods to update the status of the task. it can be overwrite to perform action such
-    // as database save ...
+    // as database save ... Illegal transitions (see Status::can_transition_to) raise an error.
      */
     fn _set_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
+        self.status().check_transition_to(&status)?;
         debug!("{}: status updated '{}' -> '{}'", self.label(Some("SetStatus")), self.status(), &status);
-        self.set_status(status)
+        let from = self.status();
+        let result = self.set_status(status.clone())?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    // Force the status without checking the transition, reserved to admin or recovery tools
+    fn force_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
+        warn!("{}: status forced '{}' -> '{}'", self.label(Some("ForceStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone())?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
     }
     fn set_status(&mut self, status: Status) -> Result<cdumay_result::Result> {
         *self.status_mut() = status;
+        self.save()?;
         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
     }
+    /***********************************************************************************************
+    // Store - Snapshots are saved by set_status and finalize into the configured store, if any.
+    // They are saved again once the final result is merged, on success, on error and on cancel.
+    // Each status change made by _set_status or force_status is recorded as a transition
+     */
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        None
+    }
+    fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot::new(self.message().entrypoint, self.status(), self.message(), self.result())
+    }
+    fn save(&self) -> Result<()> {
+        match self.store() {
+            Some(store) => store.save(&self.snapshot()),
+            None => Ok(()),
+        }
+    }
+    fn record_transition(&self, from: &Status, to: &Status) -> Result<()> {
+        match self.store() {
+            Some(store) => store.record_transition(&self.message().uuid, from, to),
+            None => Ok(()),
+        }
+    }
 
     /***********************************************************************************************
     // On Pre Build - Trigger launched on operation building
@@ -164,9 +591,39 @@ pub trait Operation {
         self.finalize()
     }
     /***********************************************************************************************
+    // Restore - Rebuild an interrupted operation from persisted snapshots (see TaskStore): the
+    // snapshot sharing the uuid of the message restores the operation itself, the others restore
+    // the tasks in the given order. Finished tasks are skipped on the next execution. Operations
+    // which cannot be rebuilt from the snapshots alone return an error.
+     */
+    fn restore(message: &Message, snapshots: Vec<TaskSnapshot>) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut operation = Self::new(message, None);
+        let mut tasks = vec![];
+        for snapshot in snapshots {
+            match snapshot.uuid == message.uuid {
+                true => {
+                    *operation.status_mut() = snapshot.status;
+                    *operation.result_mut() = snapshot.result;
+                }
+                false => {
+                    let mut task = Self::TasksItems::new(&snapshot.message, Some(snapshot.result));
+                    *task.status_mut() = snapshot.status;
+                    tasks.push(task);
+                }
+            }
+        }
+        debug!("{}: {} task(s) restored", operation.label(Some("Restore")), tasks.len());
+        *operation.tasks_mut() = tasks;
+        Ok(operation)
+    }
+    /***********************************************************************************************
     // Finalize: Finalize the task, use by operation to perform database save or so one.
      */
     fn finalize(&self) -> Result<cdumay_result::Result> {
+        self.save()?;
         let mut result = self.result();
         for task in self.tasks() {
             result = &result + &task.finalize()?;
@@ -175,25 +632,43 @@ pub trait Operation {
     }
 
     /***********************************************************************************************
-    // Operation over kafka
+    // Operation over kafka: tasks are sent using the transport of the operation if any, otherwise
+    // using their own (see TaskExec::send)
      */
+    fn transport(&self) -> Option<Arc<dyn Transport>> {
+        None
+    }
+    fn _send(&self, task: &Self::TasksItems, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
+        match self.transport() {
+            Some(transport) => {
+                debug!("{}: publish on '{}'", self.label(Some("Send")), task.topic());
+                publish_result(transport.as_ref(), &task.topic(), &task.message(), result)
+            }
+            None => task.send(result),
+        }
+    }
     fn launch(&mut self, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Waiting)?;
         self.launch_next(None, result)
     }
     fn launch_next(&mut self, task: Option<Self::TasksItems>, result: Option<cdumay_result::Result>) -> Result<cdumay_result::Result> {
         match task {
             Some(task) => match self.next(&task) {
-                Some(next) => next.send(result),
+                Some(next) => self._send(&next, result),
                 None => {
                     if let Some(result) = result {
                         *self.result_mut() = &self.result() + &result;
                     }
+                    // an operation rebuilt from its message on the consuming side is still pending
+                    if self.status() == Status::Pending {
+                        *self.result_mut() = &self.result() + &self._set_status(Status::Waiting)?;
+                    }
                     self._set_status(task.status())
                 }
             },
-            None => match self.tasks().len() > 0 {
-                true => self.tasks()[0].send(result),
-                false => Ok(cdumay_result::ResultBuilder::from(&self.message())
+            None => match self.tasks().first() {
+                Some(first) => self._send(first, result),
+                None => Ok(cdumay_result::ResultBuilder::from(&self.message())
                     .stderr("Nothing to do, empty operation !".to_string())
                     .build()),
             },
@@ -215,3 +690,234 @@ pub trait Operation {
     // to implement
     fn next(&mut self, task: &Self::TasksItems) -> Option<Self::TasksItems>;
 }
+
+// Settings shared by the operations provided by the crate (see SequentialOperation and Workflow)
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OperationSettings {
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    pub(crate) transport: Option<Arc<dyn Transport>>,
+    pub(crate) store: Option<Arc<dyn TaskStore>>,
+}
+
+/// An [`Operation`] which runs an ordered list of tasks, one after the other, feeding the result of
+/// each task into the next one.
+///
+/// ```rust
+/// use cdumay_job::{define_task, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Ok(cdumay_result::ResultBuilder::from(&self.message())
+///             .stdout(self.message().entrypoint)
+///             .build())
+///     }
+/// }
+///
+/// let message = MessageBuilder::new("deploy".to_string()).build();
+/// let mut operation = SequentialOperation::new(&message, None).with_tasks(vec![
+///     Step::new(&MessageBuilder::new("first".to_string()).build(), None),
+///     Step::new(&MessageBuilder::new("second".to_string()).build(), None),
+/// ]);
+/// operation.build().unwrap();
+///
+/// let result = operation.execute(None);
+/// assert_eq!(operation.status(), Status::Success);
+/// assert!(operation.tasks().iter().all(|task| task.status() == Status::Success));
+/// assert!(result.stdout.unwrap().ends_with("first\nsecond"));
+/// ```
+///
+/// An interrupted operation can be restored from the snapshots of the operation and its tasks,
+/// finished tasks are not run again:
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use cdumay_job::{define_task, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// static RUNS: AtomicUsize = AtomicUsize::new(0);
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         // the second run fails the first time
+///         match RUNS.fetch_add(1, Ordering::SeqCst) {
+///             1 => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+/// }
+///
+/// let message = MessageBuilder::new("deploy".to_string()).build();
+/// let mut operation = SequentialOperation::new(&message, None).with_tasks(vec![
+///     Step::new(&MessageBuilder::new("first".to_string()).build(), None),
+///     Step::new(&MessageBuilder::new("second".to_string()).build(), None),
+/// ]);
+/// assert!(operation.execute(None).is_error());
+///
+/// let mut snapshots = vec![operation.snapshot()];
+/// snapshots.extend(operation.tasks().iter().map(|task| task.snapshot()));
+///
+/// let mut restored = SequentialOperation::<Step>::restore(&message, snapshots).unwrap();
+/// assert_eq!(restored.status(), Status::Failed);
+/// restored.execute(None);
+/// assert_eq!(restored.status(), Status::Success);
+/// assert_eq!(RUNS.load(Ordering::SeqCst), 3);
+/// ```
+///
+/// Using compensation, the tasks which succeeded before a failure are undone in reverse order:
+///
+/// ```rust
+/// use std::sync::Mutex;
+/// use cdumay_job::{define_task, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// static UNDONE: Mutex<Vec<String>> = Mutex::new(vec![]);
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match self.message().entrypoint.as_str() {
+///             "migrate" => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+///     fn compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         UNDONE.lock().unwrap().push(self.message().entrypoint);
+///         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+///     }
+/// }
+///
+/// let step = |name: &str| Step::new(&MessageBuilder::new(name.to_string()).build(), None);
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("upgrade".to_string()).build(), None)
+///     .with_tasks(vec![step("backup"), step("stop"), step("migrate"), step("start")])
+///     .with_compensation(true);
+/// operation.build().unwrap();
+///
+/// let result = operation.execute(None);
+/// assert_eq!(operation.status(), Status::Compensated);
+/// assert_eq!(*UNDONE.lock().unwrap(), vec!["stop".to_string(), "backup".to_string()]);
+/// assert_eq!(operation.tasks()[0].status(), Status::Compensated);
+/// match result.retval.get("compensations") {
+///     Some(serde_value::Value::Seq(compensations)) => assert_eq!(compensations.len(), 2),
+///     _ => panic!("compensations not reported"),
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SequentialOperation<T: TaskExec> {
+    message: Message,
+    status: Status,
+    result: cdumay_result::Result,
+    tasks: Vec<T>,
+    conditions: BTreeMap<uuid::Uuid, Condition>,
+    branches: BTreeMap<uuid::Uuid, Branch>,
+    compensate: bool,
+    settings: OperationSettings,
+}
+
+impl<T: TaskExec> SequentialOperation<T> {
+    pub fn with_tasks(mut self, tasks: Vec<T>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+    pub fn add_task(mut self, task: T) -> Self {
+        self.tasks.push(task);
+        self
+    }
+    /// Sets the condition of the task identified by the uuid of its message.
+    pub fn with_condition(mut self, uuid: &uuid::Uuid, condition: Condition) -> Self {
+        self.conditions.insert(*uuid, condition);
+        self
+    }
+    /// Sets the successors of the task identified by the uuid of its message.
+    pub fn with_branch(mut self, uuid: &uuid::Uuid, branch: Branch) -> Self {
+        self.branches.insert(*uuid, branch);
+        self
+    }
+    /// Undoes the succeeded tasks if the operation fails (see [`TaskExec::compensate`]).
+    pub fn with_compensation(mut self, compensate: bool) -> Self {
+        self.compensate = compensate;
+        self
+    }
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.settings.cancellation_token = Some(token);
+        self
+    }
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.settings.transport = Some(transport);
+        self
+    }
+    pub fn with_store(mut self, store: Arc<dyn TaskStore>) -> Self {
+        self.settings.store = Some(store);
+        self
+    }
+}
+
+impl<T: TaskExec + Clone + Send> Operation for SequentialOperation<T> {
+    type TasksItems = T;
+
+    fn build_tasks(&self) -> Vec<T> {
+        self.tasks.clone()
+    }
+    fn condition(&self, task: &T) -> Option<Condition> {
+        self.conditions.get(&task.message().uuid).cloned()
+    }
+    fn branch(&self, task: &T) -> Option<Branch> {
+        self.branches.get(&task.message().uuid).cloned()
+    }
+    fn compensate_on_error(&self) -> bool {
+        self.compensate
+    }
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        self.settings.cancellation_token.clone()
+    }
+    fn transport(&self) -> Option<Arc<dyn Transport>> {
+        self.settings.transport.clone()
+    }
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        self.settings.store.clone()
+    }
+
+    fn new(message: &Message, result: Option<cdumay_result::Result>) -> Self {
+        SequentialOperation {
+            message: message.clone(),
+            status: Status::Pending,
+            result: result.unwrap_or(message.result.clone()),
+            tasks: vec![],
+            conditions: BTreeMap::new(),
+            branches: BTreeMap::new(),
+            compensate: false,
+            settings: OperationSettings::default(),
+        }
+    }
+    fn status(&self) -> Status {
+        self.status.clone()
+    }
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
+    }
+    fn message(&self) -> Message {
+        self.message.clone()
+    }
+    fn message_mut(&mut self) -> &mut Message {
+        &mut self.message
+    }
+    fn result(&self) -> cdumay_result::Result {
+        self.result.clone()
+    }
+    fn result_mut(&mut self) -> &mut cdumay_result::Result {
+        &mut self.result
+    }
+    fn tasks(&self) -> &Vec<T> {
+        &self.tasks
+    }
+    fn tasks_mut(&mut self) -> &mut Vec<T> {
+        &mut self.tasks
+    }
+
+    // tasks are identified by the uuid of their message
+    fn next(&mut self, task: &T) -> Option<T> {
+        self._next_index(task).map(|index| self.tasks[index].clone())
+    }
+}
@@ -0,0 +1,331 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::slice;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_value::{DeserializerError, Value, ValueDeserializer};
+
+use crate::errors::InvalidParams;
+
+// Value of a parameter: the one given by the message, or a placeholder standing for a parameter
+// which has already been reported as missing or invalid
+enum Param {
+    Given(Value),
+    Placeholder { fallback: bool },
+}
+
+fn invalid_params(errors: BTreeMap<String, String>) -> cdumay_error::Error {
+    InvalidParams::new()
+        .set_message(format!(
+            "Invalid params: {}",
+            errors.keys().cloned().collect::<Vec<String>>().join(", ")
+        ))
+        .set_details(BTreeMap::from([(
+            "errors".to_string(),
+            Value::Map(
+                errors
+                    .into_iter()
+                    .map(|(field, error)| (Value::String(field), Value::String(error)))
+                    .collect(),
+            ),
+        )]))
+        .into()
+}
+
+/// Deserializes the params of a message. Serde stops on the first error, so each missing or
+/// invalid field is replaced by a placeholder and the deserialization is run again, until every
+/// error has been reported. Only the first error of a field is reported. A field rejecting both
+/// placeholders is moved after the others so they are still checked, the fields missing after it
+/// are then reported once it is fixed.
+pub(crate) fn parse_params<P: DeserializeOwned>(params: Option<Value>) -> cdumay_error::Result<P> {
+    let mut fields = vec![];
+    match params {
+        None | Some(Value::Unit) | Some(Value::Option(None)) => {}
+        Some(Value::Map(map)) if map.keys().all(|key| matches!(key, Value::String(_))) => {
+            for (key, value) in map {
+                if let Value::String(key) = key {
+                    fields.push((key, Param::Given(value)));
+                }
+            }
+        }
+        Some(value) => {
+            return P::deserialize(value)
+                .map_err(|err| invalid_params(BTreeMap::from([("params".to_string(), err.to_string())])));
+        }
+    }
+    let mut errors = BTreeMap::new();
+    // number of fields rejecting both placeholders, kept at the end of the fields
+    let mut stuck = 0;
+    loop {
+        let current = RefCell::new(None);
+        let err = match P::deserialize(ParamsDeserializer {
+            fields: &fields,
+            current: &current,
+        }) {
+            Ok(params) if errors.is_empty() => return Ok(params),
+            Ok(_) => return Err(invalid_params(errors)),
+            Err(err) => err,
+        };
+        let field = match (current.into_inner(), &err) {
+            (Some(field), _) => field,
+            (None, DeserializerError::MissingField(field)) => field.to_string(),
+            (None, _) => "params".to_string(),
+        };
+        let index = fields.iter().position(|(key, _)| *key == field);
+        if !errors.contains_key(&field) && field != "params" {
+            errors.insert(field.clone(), err.to_string());
+            let placeholder = Param::Placeholder { fallback: false };
+            match (index, &err) {
+                (Some(index), DeserializerError::UnknownField(..)) => {
+                    fields.remove(index);
+                }
+                (Some(index), _) => fields[index].1 = placeholder,
+                (None, _) => fields.insert(fields.len() - stuck, (field, placeholder)),
+            }
+            continue;
+        }
+        // the placeholder of the field is rejected, its first error is kept
+        match index {
+            Some(index) if matches!(fields[index].1, Param::Placeholder { fallback: false }) => {
+                fields[index].1 = Param::Placeholder { fallback: true };
+            }
+            Some(index) if index < fields.len() - stuck => {
+                let param = fields.remove(index);
+                fields.push(param);
+                stuck += 1;
+            }
+            _ => {
+                errors.entry(field).or_insert_with(|| err.to_string());
+                return Err(invalid_params(errors));
+            }
+        }
+    }
+}
+
+// Deserializes the params as a map, keeping track of the field being deserialized
+struct ParamsDeserializer<'a> {
+    fields: &'a [(String, Param)],
+    current: &'a RefCell<Option<String>>,
+}
+
+impl<'de> de::Deserializer<'de> for ParamsDeserializer<'_> {
+    type Error = DeserializerError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(ParamsAccess {
+            fields: self.fields.iter(),
+            value: None,
+            current: self.current,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ParamsAccess<'a> {
+    fields: slice::Iter<'a, (String, Param)>,
+    value: Option<&'a Param>,
+    current: &'a RefCell<Option<String>>,
+}
+
+impl<'de> de::MapAccess<'de> for ParamsAccess<'_> {
+    type Error = DeserializerError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match self.fields.next() {
+            Some((key, param)) => {
+                self.current.replace(Some(key.clone()));
+                self.value = Some(param);
+                seed.deserialize(key.as_str().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let value = match self.value.take() {
+            Some(Param::Given(value)) => seed.deserialize(ValueDeserializer::<DeserializerError>::new(value.clone()))?,
+            Some(Param::Placeholder { fallback }) => seed.deserialize(Placeholder { fallback: *fallback })?,
+            None => return Err(de::Error::custom("value requested before key")),
+        };
+        self.current.replace(None);
+        Ok(value)
+    }
+}
+
+// Deserializes into the "zero" of any type (false, 0, "", None, empty collections...). The fallback
+// stands for the types rejecting their zero: numbers are 1 and strings the nil uuid
+#[derive(Clone, Copy)]
+struct Placeholder {
+    fallback: bool,
+}
+
+const NIL_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+impl<'de> de::Deserializer<'de> for Placeholder {
+    type Error = DeserializerError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bool(false)
+    }
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.fallback as i64)
+    }
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.fallback as i64)
+    }
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.fallback as i64)
+    }
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.fallback as i64)
+    }
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.fallback as u64)
+    }
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.fallback as u64)
+    }
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.fallback as u64)
+    }
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.fallback as u64)
+    }
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.fallback as u8 as f64)
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.fallback as u8 as f64)
+    }
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_char('\0')
+    }
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(if self.fallback { NIL_UUID } else { "" })
+    }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(if self.fallback { NIL_UUID } else { "" })
+    }
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(&[])
+    }
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(&[])
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_none()
+    }
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(0, self))
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self))
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self))
+    }
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap(Default::default(), self))
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap(fields.iter(), self))
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match variants.first() {
+            Some(variant) => visitor.visit_enum(PlaceholderVariant(variant, self)),
+            None => Err(de::Error::custom("enum without variant")),
+        }
+    }
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str("")
+    }
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct PlaceholderSeq(usize, Placeholder);
+
+impl<'de> de::SeqAccess<'de> for PlaceholderSeq {
+    type Error = DeserializerError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+        match self.0 {
+            0 => Ok(None),
+            _ => {
+                self.0 -= 1;
+                seed.deserialize(self.1).map(Some)
+            }
+        }
+    }
+}
+
+struct PlaceholderMap(slice::Iter<'static, &'static str>, Placeholder);
+
+impl<'de> de::MapAccess<'de> for PlaceholderMap {
+    type Error = DeserializerError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match self.0.next() {
+            Some(field) => seed.deserialize((*field).into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        seed.deserialize(self.1)
+    }
+}
+
+struct PlaceholderVariant(&'static str, Placeholder);
+
+impl<'de> de::EnumAccess<'de> for PlaceholderVariant {
+    type Error = DeserializerError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Self::Error> {
+        Ok((seed.deserialize(IntoDeserializer::<DeserializerError>::into_deserializer(self.0))?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for PlaceholderVariant {
+    type Error = DeserializerError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.1)
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(PlaceholderSeq(len, self.1))
+    }
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(PlaceholderMap(fields.iter(), self.1))
+    }
+}
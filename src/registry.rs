@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use log::error;
+use serde_value::Value;
+
+use crate::errors::{NotBuildable, UnknownEntrypoint};
+use crate::params::parse_params;
+use crate::{DynTask, Message, TaskExec};
+
+type TaskBuilder = fn(&Message) -> Box<dyn DynTask>;
+
+#[derive(Clone)]
+struct RegistryEntry {
+    execute: fn(&Message) -> cdumay_result::Result,
+    build: Option<TaskBuilder>,
+    validate: fn(&Message) -> cdumay_error::Result<()>,
+    schema: fn() -> Option<serde_json::Value>,
+}
+
+fn execute_task<T: TaskExec>(message: &Message) -> cdumay_result::Result {
+    T::new(message, None).execute(None)
+}
+
+fn build_task<T: TaskExec + Clone + Send + 'static>(message: &Message) -> Box<dyn DynTask> {
+    Box::new(T::new(message, None))
+}
+
+fn validate_params<T: TaskExec>(message: &Message) -> cdumay_error::Result<()> {
+    parse_params::<T::Params>(message.params.clone()).map(|_| ())
+}
+
+fn error_result(message: &Message, error: cdumay_error::Error) -> cdumay_result::Result {
+    let mut result = cdumay_result::Result::from(error);
+    result.uuid = message.uuid;
+    result
+}
+
+pub(crate) fn unknown_entrypoint(message: &Message) -> cdumay_error::Error {
+    UnknownEntrypoint::new()
+        .set_message(format!("No task registered for entrypoint '{}'", message.entrypoint))
+        .set_details(BTreeMap::from([("entrypoint".to_string(), Value::String(message.entrypoint.clone()))]))
+        .into()
+}
+
+fn not_buildable(message: &Message) -> cdumay_error::Error {
+    NotBuildable::new()
+        .set_message(format!("Task '{}' is not registered using register_dyn", message.entrypoint))
+        .set_details(BTreeMap::from([("entrypoint".to_string(), Value::String(message.entrypoint.clone()))]))
+        .into()
+}
+
+/// Maps entrypoints to task types, in order to run the task matching the entrypoint of a message.
+///
+/// ```rust
+/// use cdumay_job::errors::UnknownEntrypoint;
+/// use cdumay_job::{define_task, MessageBuilder, Registry, TaskExec};
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {}
+///
+/// let registry = Registry::default().register::<Hello>();
+///
+/// let result = registry.execute(&MessageBuilder::new(Hello::entrypoint()).build());
+/// assert!(!result.is_error());
+///
+/// let result = registry.execute(&MessageBuilder::new("unknown".to_string()).build());
+/// assert_eq!(result.retcode, UnknownEntrypoint::kind.code());
+/// ```
+#[derive(Clone, Default)]
+pub struct Registry {
+    entries: BTreeMap<String, RegistryEntry>,
+}
+
+impl Registry {
+    pub fn register<T: TaskExec>(self) -> Self {
+        self.insert::<T>(None)
+    }
+    /// Registers the task like [`Registry::register`], it can also be built as a
+    /// [`DynTask`] using [`Registry::build`].
+    pub fn register_dyn<T: TaskExec + Clone + Send + 'static>(self) -> Self {
+        self.insert::<T>(Some(build_task::<T>))
+    }
+    fn insert<T: TaskExec>(mut self, build: Option<TaskBuilder>) -> Self {
+        self.entries.insert(
+            T::entrypoint(),
+            RegistryEntry {
+                execute: execute_task::<T>,
+                build,
+                validate: validate_params::<T>,
+                schema: T::params_schema,
+            },
+        );
+        self
+    }
+    pub fn contains(&self, entrypoint: &str) -> bool {
+        self.entries.contains_key(entrypoint)
+    }
+    pub fn entrypoints(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+    /// Builds the task registered for the entrypoint of the message and executes it.
+    pub fn execute(&self, message: &Message) -> cdumay_result::Result {
+        match self.entries.get(&message.entrypoint) {
+            Some(entry) => (entry.execute)(message),
+            None => {
+                let error = unknown_entrypoint(message);
+                error!("{}[{}]: {}", message.entrypoint, message.uuid, error.message);
+                error_result(message, error)
+            }
+        }
+    }
+    /// Builds the task registered for the entrypoint of the message, without running it. Only
+    /// the tasks registered using [`Registry::register_dyn`] can be built.
+    pub fn build(&self, message: &Message) -> cdumay_error::Result<Box<dyn DynTask>> {
+        match self.entries.get(&message.entrypoint) {
+            Some(entry) => entry.build.map(|build| build(message)).ok_or_else(|| not_buildable(message)),
+            None => Err(unknown_entrypoint(message)),
+        }
+    }
+    /// Checks the params of the message against the params of the task registered for its
+    /// entrypoint, without running it. The params are deserialized into
+    /// [`TaskInfo::Params`](crate::TaskInfo::Params) as [`TaskExec::check_required_params`] does,
+    /// each missing or invalid field is reported in the `errors` of the result `retval`. The
+    /// schema of the task (see [`TaskExec::params_schema`]) is not used, generating it from the
+    /// params type keeps both in line.
+    ///
+    /// ```rust
+    /// use cdumay_job::errors::InvalidParams;
+    /// use cdumay_job::{define_task, MessageBuilder, Registry, TaskExec};
+    /// use serde_json::json;
+    /// use serde_value::Value;
+    ///
+    /// #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    /// pub enum Strategy {
+    ///     Rolling,
+    ///     Canary { percent: u8 },
+    /// }
+    ///
+    /// #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    /// pub struct Target {
+    ///     host: String,
+    ///     replicas: Option<u32>,
+    ///     strategy: Strategy,
+    ///     #[serde(default)]
+    ///     retries: u32,
+    /// }
+    ///
+    /// define_task!(Deploy, Target);
+    /// impl TaskExec for Deploy {
+    ///     fn params_schema() -> Option<serde_json::Value> {
+    ///         Some(schemars::schema_for!(Target).to_value())
+    ///     }
+    /// }
+    ///
+    /// // a task without schema is not published
+    /// define_task!(Hello);
+    /// impl TaskExec for Hello {}
+    ///
+    /// let registry = Registry::default().register::<Deploy>().register::<Hello>();
+    /// let schema = registry.schema(&Deploy::entrypoint()).unwrap();
+    /// assert_eq!(schema["properties"]["host"], json!({"type": "string"}));
+    /// assert_eq!(schema["required"], json!(["host", "strategy"]));
+    /// assert_eq!(registry.schema(&Hello::entrypoint()), None);
+    /// assert_eq!(registry.schemas().keys().collect::<Vec<_>>(), vec![&Deploy::entrypoint()]);
+    ///
+    /// let params = |params: Vec<(&str, Value)>| {
+    ///     Value::Map(params.into_iter().map(|(key, value)| (Value::String(key.to_string()), value)).collect())
+    /// };
+    /// // the fields which are not required may be left out
+    /// let message = MessageBuilder::new(Deploy::entrypoint())
+    ///     .params(params(vec![("host", Value::String("web1".to_string())), ("strategy", Value::String("Rolling".to_string()))]))
+    ///     .build();
+    /// assert!(!registry.validate(&message).is_error());
+    ///
+    /// let message = MessageBuilder::new(Deploy::entrypoint())
+    ///     .params(params(vec![("replicas", Value::String("two".to_string()))]))
+    ///     .build();
+    /// let result = registry.validate(&message);
+    /// assert_eq!(result.retcode, InvalidParams::kind.code());
+    /// match result.retval.get("errors") {
+    ///     Some(Value::Map(errors)) => assert_eq!(errors.len(), 3),
+    ///     _ => panic!("errors not reported"),
+    /// }
+    /// ```
+    pub fn validate(&self, message: &Message) -> cdumay_result::Result {
+        let checked = match self.entries.get(&message.entrypoint) {
+            Some(entry) => (entry.validate)(message),
+            None => Err(unknown_entrypoint(message)),
+        };
+        match checked {
+            Ok(()) => cdumay_result::ResultBuilder::default().uuid(message.uuid).build(),
+            Err(error) => error_result(message, error),
+        }
+    }
+    /// JSON Schema of the params of the task registered for the entrypoint, if the task has one
+    /// (see [`TaskExec::params_schema`]).
+    pub fn schema(&self, entrypoint: &str) -> Option<serde_json::Value> {
+        self.entries.get(entrypoint).and_then(|entry| (entry.schema)())
+    }
+    /// JSON Schemas of the params of every registered task having one, by entrypoint.
+    pub fn schemas(&self) -> BTreeMap<String, serde_json::Value> {
+        self.entries
+            .iter()
+            .filter_map(|(entrypoint, entry)| Some((entrypoint.clone(), (entry.schema)()?)))
+            .collect()
+    }
+}
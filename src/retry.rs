@@ -0,0 +1,125 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cdumay_error::Error;
+
+type RetryPredicate = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Delay to wait between two attempts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backoff {
+    /// Always wait the same delay.
+    Fixed(Duration),
+    /// Wait `initial * factor ^ (attempt - 1)`, capped at `max`.
+    Exponential { initial: Duration, factor: u32, max: Duration },
+}
+
+/// Retry policy of a task, returned by [`TaskExec::retry_policy`](crate::TaskExec::retry_policy).
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::time::Duration;
+/// use cdumay_job::{define_task, Backoff, MessageBuilder, RetryPolicy, Status, TaskExec, TaskInfo};
+///
+/// static CALLS: AtomicUsize = AtomicUsize::new(0);
+///
+/// define_task!(Flaky);
+///
+/// impl TaskExec for Flaky {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         match CALLS.fetch_add(1, Ordering::SeqCst) {
+///             0 | 1 => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+///     fn retry_policy(&self) -> Option<RetryPolicy> {
+///         Some(RetryPolicy::new(5).backoff(Backoff::Fixed(Duration::from_millis(1))))
+///     }
+/// }
+///
+/// let mut task = Flaky::new(&MessageBuilder::new("flaky".to_string()).build(), None);
+/// let result = task.execute(None);
+/// assert_eq!(task.status(), Status::Success);
+/// assert_eq!(result.retval.get("attempts"), Some(&serde_value::Value::U32(3)));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    jitter: Duration,
+    predicate: Option<RetryPredicate>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::Fixed(Duration::ZERO),
+            jitter: Duration::ZERO,
+            predicate: None,
+        }
+    }
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    /// Adds a random delay between zero and `jitter` to each backoff.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    /// Only retry errors matching the predicate, by default every error is retried.
+    pub fn retry_if<F: Fn(&Error) -> bool + Send + Sync + 'static>(mut self, predicate: F) -> Self {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+    /// Returns whether a new attempt must be made after the attempt number `attempt` failed.
+    pub fn should_retry(&self, attempt: u32, error: &Error) -> bool {
+        attempt < self.max_attempts && self.predicate.as_ref().is_none_or(|predicate| predicate(error))
+    }
+    /// Returns the delay to wait after the attempt number `attempt` failed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = match &self.backoff {
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { initial, factor, max } => factor
+                .checked_pow(attempt.saturating_sub(1))
+                .and_then(|factor| initial.checked_mul(factor))
+                .map_or(*max, |delay| delay.min(*max)),
+        };
+        delay + self.random_jitter()
+    }
+    fn random_jitter(&self) -> Duration {
+        match self.jitter.is_zero() {
+            true => Duration::ZERO,
+            false => Duration::from_nanos(random_u64() % (self.jitter.as_nanos() as u64).saturating_add(1)),
+        }
+    }
+}
+
+// RandomState keys are random per thread but only incremented by each RandomState::new, they are
+// not a source of randomness by themselves: the current time and a call counter are hashed using
+// them to get a different value on each call
+fn random_u64() -> u64 {
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+    hasher.write_u64(CALLS.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_value::Value;
+
+use crate::errors::StoreFailure;
+use crate::store::now_millis;
+use crate::{Status, TaskSnapshot, TaskStore};
+
+/// A status update recorded by [`SqliteStore`]. Timestamps are in milliseconds since the Unix
+/// epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusTransition {
+    pub from: Status,
+    pub to: Status,
+    pub at: u64,
+}
+
+/// Last known state of a task and every status update it went through.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskHistory {
+    pub snapshot: TaskSnapshot,
+    pub transitions: Vec<StatusTransition>,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tasks (
+    uuid TEXT PRIMARY KEY,
+    entrypoint TEXT NOT NULL,
+    status TEXT NOT NULL,
+    message TEXT NOT NULL,
+    result_uuid TEXT NOT NULL,
+    retcode INTEGER NOT NULL,
+    stdout TEXT,
+    stderr TEXT,
+    retval TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tasks_entrypoint ON tasks (entrypoint);
+CREATE INDEX IF NOT EXISTS tasks_status ON tasks (status);
+CREATE TABLE IF NOT EXISTS transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS transitions_uuid ON transitions (uuid);
+";
+
+const SELECT_TASKS: &str = "SELECT uuid, entrypoint, status, message, result_uuid, retcode, stdout, stderr, retval, \
+created_at, updated_at FROM tasks";
+
+fn store_error<E: ToString>(err: E) -> cdumay_error::Error {
+    StoreFailure::new().set_message(err.to_string()).into()
+}
+
+fn conversion_error<E: ToString>(index: usize, err: E) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, err.to_string().into())
+}
+
+fn parse_status(index: usize, value: String) -> rusqlite::Result<Status> {
+    Status::from_str(&value).map_err(|err| conversion_error(index, err.message))
+}
+
+fn read_snapshot(row: &Row) -> rusqlite::Result<TaskSnapshot> {
+    let message: String = row.get(3)?;
+    let result_uuid: String = row.get(4)?;
+    let retval: String = row.get(8)?;
+    Ok(TaskSnapshot {
+        uuid: uuid::Uuid::parse_str(&row.get::<_, String>(0)?).map_err(|err| conversion_error(0, err))?,
+        entrypoint: row.get(1)?,
+        status: parse_status(2, row.get(2)?)?,
+        message: serde_json::from_str(&message).map_err(|err| conversion_error(3, err))?,
+        result: cdumay_result::Result {
+            uuid: uuid::Uuid::parse_str(&result_uuid).map_err(|err| conversion_error(4, err))?,
+            retcode: row.get(5)?,
+            stdout: row.get(6)?,
+            stderr: row.get(7)?,
+            retval: serde_json::from_str::<BTreeMap<String, Value>>(&retval).map_err(|err| conversion_error(8, err))?,
+        },
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+/// [`TaskStore`] backed by SQLite, available using the feature `sqlite`. In addition to the last
+/// snapshot of each task, every status update made by `_set_status` or `force_status` is recorded
+/// (see [`TaskStore::record_transition`]) to keep the job history.
+///
+/// ```rust
+/// use std::sync::{Arc, LazyLock};
+/// use cdumay_job::errors::TaskPanicked;
+/// use cdumay_job::{define_task, MessageBuilder, SqliteStore, Status, TaskExec, TaskInfo, TaskStore};
+///
+/// static STORE: LazyLock<Arc<SqliteStore>> = LazyLock::new(|| Arc::new(SqliteStore::open_in_memory().unwrap()));
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {
+///     fn store(&self) -> Option<Arc<dyn TaskStore>> {
+///         Some(STORE.clone())
+///     }
+/// }
+///
+/// let mut task = Hello::new(&MessageBuilder::new("hello".to_string()).build(), None);
+/// task.execute(None);
+///
+/// let history = STORE.history(&task.message().uuid).unwrap().unwrap();
+/// let statuses: Vec<(Status, Status)> =
+///     history.transitions.iter().map(|transition| (transition.from.clone(), transition.to.clone())).collect();
+/// assert_eq!(statuses, vec![(Status::Pending, Status::Running), (Status::Running, Status::Success)]);
+/// assert_eq!(STORE.history_by_status(&Status::Success).unwrap().len(), 1);
+/// assert_eq!(STORE.history_by_entrypoint(&Hello::entrypoint()).unwrap().len(), 1);
+///
+/// // the history of a failed task keeps its final result
+/// define_task!(Broken);
+/// impl TaskExec for Broken {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Err(TaskPanicked::new().set_message("disk full".to_string()).into())
+///     }
+///     fn store(&self) -> Option<Arc<dyn TaskStore>> {
+///         Some(STORE.clone())
+///     }
+/// }
+///
+/// let mut task = Broken::new(&MessageBuilder::new("broken".to_string()).build(), None);
+/// task.execute(None);
+///
+/// let history = STORE.history(&task.message().uuid).unwrap().unwrap();
+/// let statuses: Vec<Status> = history.transitions.iter().map(|transition| transition.to.clone()).collect();
+/// assert_eq!(statuses, vec![Status::Running, Status::Failed]);
+/// assert_eq!(history.snapshot.result.retcode, TaskPanicked::kind.code());
+/// assert!(history.snapshot.result.stderr.unwrap().ends_with("disk full"));
+/// ```
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> cdumay_error::Result<Self> {
+        Self::init(Connection::open(path).map_err(store_error)?)
+    }
+    pub fn open_in_memory() -> cdumay_error::Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(store_error)?)
+    }
+    fn init(connection: Connection) -> cdumay_error::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(store_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+    fn transitions(connection: &Connection, uuid: &uuid::Uuid) -> rusqlite::Result<Vec<StatusTransition>> {
+        let mut statement =
+            connection.prepare("SELECT from_status, to_status, at FROM transitions WHERE uuid = ?1 ORDER BY id")?;
+        let rows = statement.query_map(params![uuid.to_string()], |row| {
+            Ok(StatusTransition {
+                from: parse_status(0, row.get(0)?)?,
+                to: parse_status(1, row.get(1)?)?,
+                at: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+    fn histories(&self, filter: &str, value: String) -> cdumay_error::Result<Vec<TaskHistory>> {
+        let connection = self.connection.lock().map_err(store_error)?;
+        let mut statement = connection
+            .prepare(&format!("{} WHERE {} = ?1 ORDER BY created_at", SELECT_TASKS, filter))
+            .map_err(store_error)?;
+        let snapshots = statement
+            .query_map(params![value], read_snapshot)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<TaskSnapshot>>>())
+            .map_err(store_error)?;
+        snapshots
+            .into_iter()
+            .map(|snapshot| {
+                Ok(TaskHistory {
+                    transitions: Self::transitions(&connection, &snapshot.uuid).map_err(store_error)?,
+                    snapshot,
+                })
+            })
+            .collect()
+    }
+    pub fn history(&self, uuid: &uuid::Uuid) -> cdumay_error::Result<Option<TaskHistory>> {
+        Ok(self.histories("uuid", uuid.to_string())?.pop())
+    }
+    pub fn history_by_entrypoint(&self, entrypoint: &str) -> cdumay_error::Result<Vec<TaskHistory>> {
+        self.histories("entrypoint", entrypoint.to_string())
+    }
+    pub fn history_by_status(&self, status: &Status) -> cdumay_error::Result<Vec<TaskHistory>> {
+        self.histories("status", status.to_string())
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn save(&self, snapshot: &TaskSnapshot) -> cdumay_error::Result<()> {
+        let connection = self.connection.lock().map_err(store_error)?;
+        connection
+            .execute(
+                "INSERT INTO tasks (uuid, entrypoint, status, message, result_uuid, retcode, stdout, stderr, retval, \
+                 created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11) \
+                 ON CONFLICT (uuid) DO UPDATE SET entrypoint = excluded.entrypoint, status = excluded.status, \
+                 message = excluded.message, result_uuid = excluded.result_uuid, retcode = excluded.retcode, \
+                 stdout = excluded.stdout, stderr = excluded.stderr, retval = excluded.retval, \
+                 updated_at = excluded.updated_at",
+                params![
+                    snapshot.uuid.to_string(),
+                    snapshot.entrypoint,
+                    snapshot.status.to_string(),
+                    serde_json::to_string(&snapshot.message).map_err(store_error)?,
+                    snapshot.result.uuid.to_string(),
+                    snapshot.result.retcode,
+                    snapshot.result.stdout,
+                    snapshot.result.stderr,
+                    serde_json::to_string(&snapshot.result.retval).map_err(store_error)?,
+                    snapshot.created_at,
+                    snapshot.updated_at,
+                ],
+            )
+            .map(|_| ())
+            .map_err(store_error)
+    }
+    fn load(&self, uuid: &uuid::Uuid) -> cdumay_error::Result<Option<TaskSnapshot>> {
+        let connection = self.connection.lock().map_err(store_error)?;
+        connection
+            .query_row(&format!("{} WHERE uuid = ?1", SELECT_TASKS), params![uuid.to_string()], read_snapshot)
+            .optional()
+            .map_err(store_error)
+    }
+    fn record_transition(&self, uuid: &uuid::Uuid, from: &Status, to: &Status) -> cdumay_error::Result<()> {
+        let connection = self.connection.lock().map_err(store_error)?;
+        connection
+            .execute(
+                "INSERT INTO transitions (uuid, from_status, to_status, at) VALUES (?1, ?2, ?3, ?4)",
+                params![uuid.to_string(), from.to_string(), to.to_string(), now_millis()],
+            )
+            .map(|_| ())
+            .map_err(store_error)
+    }
+}
@@ -1,15 +1,126 @@
 use core::fmt;
+use std::collections::BTreeMap;
+use std::str::FromStr;
 
+use cdumay_error::Error;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_value::Value;
 
+use crate::errors::{InvalidStatus, InvalidTransition};
+
+/// Status of a task or an operation. It is serialized using its upper-case name (`"PENDING"`,
+/// `"RUNNING"`...), unknown names are rejected.
+///
+/// ```rust
+/// use cdumay_job::Status;
+///
+/// assert_eq!(serde_json::to_string(&Status::Running).unwrap(), r#""RUNNING""#);
+/// assert_eq!(serde_json::to_string(&Status::TimedOut).unwrap(), r#""TIMED_OUT""#);
+/// assert_eq!(serde_json::from_str::<Status>(r#""SUCCESS""#).unwrap(), Status::Success);
+/// assert!(serde_json::from_str::<Status>(r#""DONE""#).is_err());
+/// assert!(Status::try_from(serde_value::Value::String("DONE".to_string())).is_err());
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 pub enum Status {
     Pending,
     Running,
     Success,
     Failed,
+    Cancelled,
+    Skipped,
+    Retrying,
+    TimedOut,
+    Waiting,
+    /// Every succeeded task has been undone after a failure.
+    Compensated,
+    /// At least one succeeded task could not be undone after a failure.
+    CompensationFailed,
 }
 
+impl Status {
+    /// Returns whether the status can be updated to `status`, updating a status to itself is
+    /// always allowed.
+    ///
+    /// ```rust
+    /// use cdumay_job::Status;
+    ///
+    /// assert!(Status::Pending.can_transition_to(&Status::Running));
+    /// assert!(Status::Running.can_transition_to(&Status::Success));
+    /// assert!(!Status::Success.can_transition_to(&Status::Running));
+    /// ```
+    pub fn can_transition_to(&self, status: &Status) -> bool {
+        self == status
+            || matches!(
+                (self, status),
+                (
+                    Status::Pending,
+                    Status::Running
+                        | Status::Waiting
+                        | Status::Retrying
+                        | Status::Skipped
+                        | Status::Cancelled
+                        | Status::Failed
+                ) | (
+                    Status::Waiting,
+                    Status::Running
+                        | Status::Success
+                        | Status::Failed
+                        | Status::Skipped
+                        | Status::Cancelled
+                        | Status::TimedOut
+                ) | (
+                    Status::Running,
+                    Status::Success
+                        | Status::Failed
+                        | Status::Cancelled
+                        | Status::TimedOut
+                        | Status::Retrying
+                        | Status::Waiting
+                ) | (
+                    Status::Retrying,
+                    Status::Running | Status::Failed | Status::Cancelled | Status::TimedOut
+                ) | (Status::TimedOut, Status::Running | Status::Retrying | Status::Failed)
+                    | (Status::Failed, Status::Running | Status::Retrying)
+                    | (
+                        Status::Success | Status::Failed | Status::TimedOut,
+                        Status::Compensated | Status::CompensationFailed
+                    )
+                    | (Status::CompensationFailed, Status::Compensated)
+            )
+    }
+    /// Same as [`Status::can_transition_to`] but returns an
+    /// [`InvalidTransition`](crate::errors::InvalidTransition) error on illegal transition.
+    ///
+    /// ```rust
+    /// use cdumay_job::errors::InvalidTransition;
+    /// use cdumay_job::{define_task, MessageBuilder, Status, TaskExec, TaskInfo};
+    ///
+    /// define_task!(Hello);
+    /// impl TaskExec for Hello {}
+    ///
+    /// let mut task = Hello::new(&MessageBuilder::new("hello".to_string()).build(), None);
+    /// assert!(!task.execute(None).is_error());
+    ///
+    /// // a successful task cannot be run again, unless its status is forced
+    /// let err = task.unsafe_execute(None).unwrap_err();
+    /// assert_eq!(err.kind, InvalidTransition::kind);
+    /// task.force_status(Status::Pending).unwrap();
+    /// assert!(task.unsafe_execute(None).is_ok());
+    /// ```
+    pub fn check_transition_to(&self, status: &Status) -> Result<(), Error> {
+        match self.can_transition_to(status) {
+            true => Ok(()),
+            false => Err(InvalidTransition::new()
+                .set_message(format!("Invalid status transition '{}' -> '{}'", self, status))
+                .set_details(BTreeMap::from([
+                    ("from".to_string(), Value::from(self.clone())),
+                    ("to".to_string(), Value::from(status.clone())),
+                ]))
+                .into()),
+        }
+    }
+}
 
 impl From<&Value> for Status {
     fn from(value: &Value) -> Status {
@@ -18,6 +129,13 @@ impl From<&Value> for Status {
                 "RUNNING" => Status::Running,
                 "SUCCESS" => Status::Success,
                 "FAILED" => Status::Failed,
+                "CANCELLED" => Status::Cancelled,
+                "SKIPPED" => Status::Skipped,
+                "RETRYING" => Status::Retrying,
+                "TIMED_OUT" => Status::TimedOut,
+                "WAITING" => Status::Waiting,
+                "COMPENSATED" => Status::Compensated,
+                "COMPENSATION_FAILED" => Status::CompensationFailed,
                 _ => Status::Pending
             },
             _ => Status::Pending
@@ -25,6 +143,44 @@ impl From<&Value> for Status {
     }
 }
 
+// `From<&Value>` already provides an infallible `TryFrom<&Value>`, so the strict conversion
+// is implemented on the owned value.
+impl TryFrom<Value> for Status {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Status, Error> {
+        match value {
+            Value::String(data) => Status::from_str(&data),
+            _ => Err(InvalidStatus::new()
+                .set_message(format!("Invalid status value: {:?}", value))
+                .into()),
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Status, Error> {
+        match value {
+            "PENDING" => Ok(Status::Pending),
+            "RUNNING" => Ok(Status::Running),
+            "SUCCESS" => Ok(Status::Success),
+            "FAILED" => Ok(Status::Failed),
+            "CANCELLED" => Ok(Status::Cancelled),
+            "SKIPPED" => Ok(Status::Skipped),
+            "RETRYING" => Ok(Status::Retrying),
+            "TIMED_OUT" => Ok(Status::TimedOut),
+            "WAITING" => Ok(Status::Waiting),
+            "COMPENSATED" => Ok(Status::Compensated),
+            "COMPENSATION_FAILED" => Ok(Status::CompensationFailed),
+            _ => Err(InvalidStatus::new()
+                .set_message(format!("Unknown status '{}'", value))
+                .into()),
+        }
+    }
+}
+
 impl From<Status> for String {
     fn from(status: Status) -> String {
         match status {
@@ -32,6 +188,13 @@ impl From<Status> for String {
             Status::Running => "RUNNING".to_string(),
             Status::Success => "SUCCESS".to_string(),
             Status::Failed => "FAILED".to_string(),
+            Status::Cancelled => "CANCELLED".to_string(),
+            Status::Skipped => "SKIPPED".to_string(),
+            Status::Retrying => "RETRYING".to_string(),
+            Status::TimedOut => "TIMED_OUT".to_string(),
+            Status::Waiting => "WAITING".to_string(),
+            Status::Compensated => "COMPENSATED".to_string(),
+            Status::CompensationFailed => "COMPENSATION_FAILED".to_string(),
         }
     }
 }
@@ -46,4 +209,17 @@ impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "{}", String::from(self.clone()))
     }
-}
\ No newline at end of file
+}
+
+impl Serialize for Status {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from(self.clone()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Status {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Status, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Status::from_str(&value).map_err(|err| de::Error::custom(err.message))
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_value::Value;
+
+use crate::errors::StoreFailure;
+use crate::{Message, Status};
+
+/// State of a task (or an operation) at a given time. Timestamps are in milliseconds since the
+/// Unix epoch.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskSnapshot {
+    pub uuid: uuid::Uuid,
+    pub entrypoint: String,
+    pub status: Status,
+    pub message: Message,
+    pub result: cdumay_result::Result,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl TaskSnapshot {
+    pub fn new(entrypoint: String, status: Status, message: Message, result: cdumay_result::Result) -> Self {
+        let now = now_millis();
+        Self {
+            uuid: message.uuid,
+            entrypoint,
+            status,
+            message,
+            result,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Persists task snapshots, used by the default `set_status` and `finalize` of tasks and
+/// operations. Saving a snapshot which already exists keeps its creation date.
+pub trait TaskStore: Debug + Send + Sync {
+    fn save(&self, snapshot: &TaskSnapshot) -> cdumay_error::Result<()>;
+    fn load(&self, uuid: &uuid::Uuid) -> cdumay_error::Result<Option<TaskSnapshot>>;
+    /// Records a status change, called by `_set_status` and `force_status` once the status is
+    /// set. Nothing is recorded by default.
+    fn record_transition(&self, _uuid: &uuid::Uuid, _from: &Status, _to: &Status) -> cdumay_error::Result<()> {
+        Ok(())
+    }
+}
+
+fn store_error(message: String, uuid: &uuid::Uuid) -> cdumay_error::Error {
+    StoreFailure::new()
+        .set_message(message)
+        .set_details(BTreeMap::from([("uuid".to_string(), Value::String(uuid.to_string()))]))
+        .into()
+}
+
+/// [`TaskStore`] keeping snapshots in memory.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use cdumay_job::errors::TaskPanicked;
+/// use cdumay_job::{define_task, MemoryStore, MessageBuilder, Status, TaskExec, TaskInfo, TaskStore};
+///
+/// static STORE: std::sync::LazyLock<Arc<MemoryStore>> = std::sync::LazyLock::new(Default::default);
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {
+///     fn store(&self) -> Option<Arc<dyn TaskStore>> {
+///         Some(STORE.clone())
+///     }
+/// }
+///
+/// let mut task = Hello::new(&MessageBuilder::new("hello".to_string()).build(), None);
+/// task.execute(None);
+///
+/// let snapshot = STORE.load(&task.message().uuid).unwrap().unwrap();
+/// assert_eq!(snapshot.status, Status::Success);
+/// assert_eq!(snapshot.entrypoint, Hello::entrypoint());
+///
+/// // the snapshot of a failed task holds the error
+/// define_task!(Broken);
+/// impl TaskExec for Broken {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         Err(TaskPanicked::new().set_message("disk full".to_string()).into())
+///     }
+///     fn store(&self) -> Option<Arc<dyn TaskStore>> {
+///         Some(STORE.clone())
+///     }
+/// }
+///
+/// let mut task = Broken::new(&MessageBuilder::new("broken".to_string()).build(), None);
+/// task.execute(None);
+///
+/// let snapshot = STORE.load(&task.message().uuid).unwrap().unwrap();
+/// assert_eq!(snapshot.status, Status::Failed);
+/// assert_eq!(snapshot.result.retcode, TaskPanicked::kind.code());
+/// assert!(snapshot.result.stderr.unwrap().ends_with("disk full"));
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    snapshots: Mutex<BTreeMap<uuid::Uuid, TaskSnapshot>>,
+}
+
+impl TaskStore for MemoryStore {
+    fn save(&self, snapshot: &TaskSnapshot) -> cdumay_error::Result<()> {
+        let mut snapshots = self.snapshots.lock().map_err(|err| store_error(err.to_string(), &snapshot.uuid))?;
+        let mut snapshot = snapshot.clone();
+        if let Some(previous) = snapshots.get(&snapshot.uuid) {
+            snapshot.created_at = previous.created_at;
+        }
+        snapshots.insert(snapshot.uuid, snapshot);
+        Ok(())
+    }
+    fn load(&self, uuid: &uuid::Uuid) -> cdumay_error::Result<Option<TaskSnapshot>> {
+        let snapshots = self.snapshots.lock().map_err(|err| store_error(err.to_string(), uuid))?;
+        Ok(snapshots.get(uuid).cloned())
+    }
+}
+
+/// [`TaskStore`] writing each snapshot in a JSON file named after the task uuid.
+///
+/// ```rust
+/// use cdumay_job::{FileStore, MessageBuilder, Status, TaskSnapshot, TaskStore};
+///
+/// let store = FileStore::new(std::env::temp_dir().join(format!("store-{}", uuid::Uuid::new_v4())));
+/// let message = MessageBuilder::new("hello".to_string()).build();
+/// let result = message.result.clone();
+/// store.save(&TaskSnapshot::new("hello".to_string(), Status::Running, message.clone(), result)).unwrap();
+///
+/// assert_eq!(store.load(&message.uuid).unwrap().unwrap().status, Status::Running);
+/// assert!(store.load(&uuid::Uuid::new_v4()).unwrap().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+    fn path(&self, uuid: &uuid::Uuid) -> PathBuf {
+        self.directory.join(format!("{}.json", uuid))
+    }
+}
+
+impl TaskStore for FileStore {
+    fn save(&self, snapshot: &TaskSnapshot) -> cdumay_error::Result<()> {
+        let mut snapshot = snapshot.clone();
+        if let Some(previous) = self.load(&snapshot.uuid)? {
+            snapshot.created_at = previous.created_at;
+        }
+        fs::create_dir_all(&self.directory).map_err(|err| store_error(err.to_string(), &snapshot.uuid))?;
+        let data = serde_json::to_vec_pretty(&snapshot).map_err(|err| store_error(err.to_string(), &snapshot.uuid))?;
+        // the file is renamed once written, so that readers never see a partial snapshot
+        let tmp = self.directory.join(format!("{}.tmp", snapshot.uuid));
+        fs::write(&tmp, data).map_err(|err| store_error(err.to_string(), &snapshot.uuid))?;
+        fs::rename(&tmp, self.path(&snapshot.uuid)).map_err(|err| store_error(err.to_string(), &snapshot.uuid))
+    }
+    fn load(&self, uuid: &uuid::Uuid) -> cdumay_error::Result<Option<TaskSnapshot>> {
+        let path = self.path(uuid);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(path).map_err(|err| store_error(err.to_string(), uuid))?;
+        Ok(Some(serde_json::from_slice(&data).map_err(|err| store_error(err.to_string(), uuid))?))
+    }
+}
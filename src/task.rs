@@ -1,10 +1,25 @@
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use serde_value::Value;
+use std::collections::BTreeMap;
 use std::ops::Add;
+use std::sync::Arc;
+use std::thread;
 
-use crate::{Message, Status};
+use crate::errors::{InvalidOutput, TaskCancelled, TaskTimeout};
+use crate::params::parse_params;
+use crate::transport::publish_result;
+use crate::{CancellationToken, Message, RetryPolicy, Status, TaskSnapshot, TaskStore, Timeout, Transport};
 
 pub trait TaskInfo {
+    /// Type of the message params, `serde_value::Value` accepts any params.
+    type Params: DeserializeOwned;
+    /// Type of the task output, `serde_value::Value` accepts any output.
+    type Output: Serialize + DeserializeOwned;
+    /// Key of the result `retval` holding the task output.
+    const OUTPUT_KEY: &'static str = "output";
+
     fn new(msg: &Message, result: Option<cdumay_result::Result>) -> Self;
     fn path() -> String;
     fn status(&self) -> Status;
@@ -13,31 +28,296 @@ pub trait TaskInfo {
     fn message_mut(&mut self) -> &mut Message;
     fn result(&self) -> cdumay_result::Result;
     fn result_mut(&mut self) -> &mut cdumay_result::Result;
+    /// Looks up `key` in the `retval` of the task result, then in the one of the message result.
+    /// `key` may be a dotted path (`db.primary.host`) or a JSON pointer (`/db/primary/host`) to
+    /// reach values nested in maps and sequences.
+    ///
+    /// ```rust
+    /// use cdumay_job::{define_task, MessageBuilder, TaskInfo};
+    /// use serde_value::Value;
+    ///
+    /// define_task!(Connect);
+    ///
+    /// let map = |entries: Vec<(&str, Value)>| {
+    ///     Value::Map(entries.into_iter().map(|(key, value)| (Value::String(key.to_string()), value)).collect())
+    /// };
+    /// let db = map(vec![("primary", map(vec![("host", Value::String("db1".to_string()))]))]);
+    /// let message = MessageBuilder::new("connect".to_string())
+    ///     .result(cdumay_result::ResultBuilder::default().retval([("db".to_string(), db)].into()).build())
+    ///     .metadata([("hosts".to_string(), Value::Seq(vec![Value::String("web1".to_string())]))].into())
+    ///     .build();
+    /// let mut task = Connect::new(&message, None);
+    /// task.result_mut().retval.insert("port".to_string(), Value::U16(5432));
+    ///
+    /// assert_eq!(task.search_result("port").unwrap(), Some(Value::U16(5432)));
+    /// assert_eq!(task.search_result("db.primary.host").unwrap(), Some(Value::String("db1".to_string())));
+    /// assert_eq!(task.search_result("/db/primary/host").unwrap(), Some(Value::String("db1".to_string())));
+    /// assert_eq!(task.search_result("db.replica.host").unwrap(), None);
+    /// assert_eq!(task.search_meta("hosts.0").unwrap(), Some(Value::String("web1".to_string())));
+    /// ```
     fn search_result(&self, key: &str) -> cdumay_error::Result<Option<Value>> {
-        match self.message().result.retval.get(key) {
-            Some(value) => Ok(Some(value.clone())),
-            None => Ok(None),
+        match lookup(&self.result().retval, key) {
+            Some(value) => Ok(Some(value)),
+            None => Ok(lookup(&self.message().result.retval, key)),
         }
     }
-    fn search_meta(&self, key: &str) -> cdumay_error::Result<Option<Value>> {
-        match self.message().metadata.get(key) {
-            Some(value) => Ok(Some(value.clone())),
+    /// Deserializes the value of `key` found by [`TaskInfo::search_result`], a value which does not
+    /// match `T` raises an [`InvalidOutput`](crate::errors::InvalidOutput) error.
+    fn search_result_as<T: DeserializeOwned>(&self, key: &str) -> cdumay_error::Result<Option<T>> {
+        match self.search_result(key)? {
+            Some(value) => value.deserialize_into().map(Some).map_err(|err| invalid_output(key, err)),
             None => Ok(None),
         }
     }
+    /// Looks up `key` in the metadata of the message, see [`TaskInfo::search_result`] for the paths.
+    fn search_meta(&self, key: &str) -> cdumay_error::Result<Option<Value>> {
+        Ok(lookup(&self.message().metadata, key))
+    }
+    /// Deserializes the message params, missing params are read as an empty map. Every missing or
+    /// invalid field is reported in the details of the [`InvalidParams`](crate::errors::InvalidParams)
+    /// error. Params are checked before running the task by
+    /// [`TaskExec::check_required_params`].
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    /// use cdumay_job::errors::InvalidParams;
+    /// use cdumay_job::{define_task, MessageBuilder, Status, TaskExec, TaskInfo};
+    /// use serde_value::Value;
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// pub struct Target {
+    ///     host: String,
+    ///     port: u16,
+    ///     replicas: Option<u32>,
+    /// }
+    ///
+    /// define_task!(Deploy, Target);
+    /// impl TaskExec for Deploy {}
+    ///
+    /// let params = |params: Vec<(&str, Value)>| {
+    ///     Value::Map(params.into_iter().map(|(key, value)| (Value::String(key.to_string()), value)).collect())
+    /// };
+    /// let message = MessageBuilder::new("deploy".to_string())
+    ///     .params(params(vec![("host", Value::String("db1".to_string())), ("port", Value::U64(5432))]))
+    ///     .build();
+    /// let target = Deploy::new(&message, None).params().unwrap();
+    /// assert_eq!((target.host.as_str(), target.port, target.replicas), ("db1", 5432, None));
+    ///
+    /// // the task is not run, both errors are reported
+    /// let message = MessageBuilder::new("deploy".to_string())
+    ///     .params(params(vec![("port", Value::String("eighty".to_string()))]))
+    ///     .build();
+    /// let mut task = Deploy::new(&message, None);
+    /// let result = task.execute(None);
+    /// assert_eq!(task.status(), Status::Failed);
+    /// assert_eq!(result.retcode, InvalidParams::kind.code());
+    /// match result.retval.get("errors") {
+    ///     Some(Value::Map(errors)) => assert_eq!(
+    ///         errors.keys().cloned().collect::<Vec<Value>>(),
+    ///         vec![Value::String("host".to_string()), Value::String("port".to_string())]
+    ///     ),
+    ///     _ => panic!("errors not reported"),
+    /// }
+    /// ```
+    ///
+    /// The first error of each field is reported, whatever the type of the field:
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    /// use std::num::NonZeroU32;
+    /// use cdumay_job::{define_task, MessageBuilder, TaskExec, TaskInfo};
+    /// use serde_value::Value;
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// pub enum Mode {
+    ///     Fast,
+    ///     Safe,
+    /// }
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// #[serde(deny_unknown_fields)]
+    /// pub struct Database {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// #[serde(deny_unknown_fields)]
+    /// pub struct Migration {
+    ///     id: uuid::Uuid,
+    ///     batch: NonZeroU32,
+    ///     database: Database,
+    ///     mode: Mode,
+    ///     owner: String,
+    /// }
+    ///
+    /// // a validated type which accepts no placeholder
+    /// #[derive(Debug, serde::Deserialize)]
+    /// #[serde(try_from = "String")]
+    /// pub struct Email(String);
+    ///
+    /// impl TryFrom<String> for Email {
+    ///     type Error = String;
+    ///     fn try_from(value: String) -> Result<Self, String> {
+    ///         match value.contains('@') {
+    ///             true => Ok(Email(value)),
+    ///             false => Err(format!("invalid email '{}'", value)),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// pub struct Notify {
+    ///     email: Email,
+    ///     retries: u8,
+    /// }
+    ///
+    /// define_task!(Migrate, Migration);
+    /// impl TaskExec for Migrate {}
+    /// define_task!(Notifier, Notify);
+    /// impl TaskExec for Notifier {}
+    ///
+    /// fn errors_of<T: TaskInfo>(params: Value) -> BTreeMap<String, String> {
+    ///     let error = match T::new(&MessageBuilder::new("errors".to_string()).params(params).build(), None).params() {
+    ///         Ok(_) => panic!("params accepted"),
+    ///         Err(error) => error,
+    ///     };
+    ///     match cdumay_result::Result::from(error).retval.get("errors") {
+    ///         Some(Value::Map(errors)) => errors
+    ///             .iter()
+    ///             .map(|(field, error)| match (field, error) {
+    ///                 (Value::String(field), Value::String(error)) => (field.clone(), error.clone()),
+    ///                 _ => panic!("invalid error"),
+    ///             })
+    ///             .collect(),
+    ///         _ => panic!("errors not reported"),
+    ///     }
+    /// }
+    /// let map = |entries: Vec<(&str, Value)>| {
+    ///     Value::Map(entries.into_iter().map(|(key, value)| (Value::String(key.to_string()), value)).collect())
+    /// };
+    ///
+    /// let errors = errors_of::<Migrate>(map(vec![
+    ///     ("database", map(vec![("host", Value::String("db1".to_string())), ("port", Value::String("x".to_string()))])),
+    ///     ("mode", Value::String("Slow".to_string())),
+    ///     ("extra", Value::Bool(true)),
+    /// ]));
+    /// assert_eq!(errors.keys().collect::<Vec<_>>(), vec!["batch", "database", "extra", "id", "mode", "owner"]);
+    /// assert_eq!(errors["id"], "Missing field id");
+    /// assert_eq!(errors["batch"], "Missing field batch");
+    /// assert_eq!(errors["database"], "Invalid type string \"x\". Expected u16");
+    /// assert!(errors["mode"].starts_with("Unknown variant Slow"));
+    /// assert!(errors["extra"].starts_with("Unknown field extra"));
+    ///
+    /// // the other fields are checked even if a field cannot be replaced by a placeholder
+    /// let errors = errors_of::<Notifier>(map(vec![("retries", Value::String("many".to_string()))]));
+    /// assert_eq!(errors["email"], "Missing field email");
+    /// assert!(errors["retries"].starts_with("Invalid type string \"many\""));
+    ///
+    /// // params which are not a map
+    /// let errors = errors_of::<Migrate>(Value::Seq(vec![]));
+    /// assert_eq!(errors.keys().collect::<Vec<_>>(), vec!["params"]);
+    /// ```
+    fn params(&self) -> cdumay_error::Result<Self::Params> {
+        parse_params(self.message().params)
+    }
     fn new_result(&self) -> cdumay_result::Result {
         cdumay_result::ResultBuilder::default().uuid(self.message().uuid).build()
     }
+    /// Builds a new result holding `output` in its `retval` under [`TaskInfo::OUTPUT_KEY`], the
+    /// output of the previous task is then read using [`TaskInfo::search_result_as`].
+    ///
+    /// ```rust
+    /// use cdumay_job::errors::InvalidOutput;
+    /// use cdumay_job::{define_task, MessageBuilder, TaskExec, TaskInfo};
+    ///
+    /// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    /// pub struct Archive {
+    ///     path: String,
+    ///     size: u64,
+    /// }
+    ///
+    /// define_task!(Backup, serde_value::Value, Archive);
+    /// impl TaskExec for Backup {
+    ///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+    ///         self.output_result(&Archive { path: "/backup/db.tar".to_string(), size: 1024 })
+    ///     }
+    /// }
+    ///
+    /// define_task!(Upload);
+    /// impl TaskExec for Upload {}
+    ///
+    /// let mut backup = Backup::new(&MessageBuilder::new("backup".to_string()).build(), None);
+    /// let result = backup.execute(None);
+    /// let archive = Archive { path: "/backup/db.tar".to_string(), size: 1024 };
+    /// assert_eq!(backup.output().unwrap(), Some(archive));
+    ///
+    /// let upload = Upload::new(&MessageBuilder::new("upload".to_string()).result(result).build(), None);
+    /// let archive: Option<Archive> = upload.search_result_as(Backup::OUTPUT_KEY).unwrap();
+    /// assert_eq!(archive.unwrap().size, 1024);
+    /// let err = upload.search_result_as::<String>(Backup::OUTPUT_KEY).unwrap_err();
+    /// assert_eq!(err.kind, InvalidOutput::kind);
+    /// ```
+    fn output_result(&self, output: &Self::Output) -> cdumay_error::Result<cdumay_result::Result> {
+        let value = serde_value::to_value(output).map_err(|err| invalid_output(Self::OUTPUT_KEY, err))?;
+        let mut result = self.new_result();
+        result.retval.insert(Self::OUTPUT_KEY.to_string(), value);
+        Ok(result)
+    }
+    /// Deserializes the output stored in the result of the task, see [`TaskInfo::output_result`].
+    fn output(&self) -> cdumay_error::Result<Option<Self::Output>> {
+        match self.result().retval.get(Self::OUTPUT_KEY) {
+            Some(value) => value.clone().deserialize_into().map(Some).map_err(|err| invalid_output(Self::OUTPUT_KEY, err)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Looks up a key, a dotted path or a JSON pointer. A key holding a dot is matched as is before
+/// being read as a path.
+fn lookup(map: &BTreeMap<String, Value>, key: &str) -> Option<Value> {
+    if let Some(value) = map.get(key) {
+        return Some(value.clone());
+    }
+    let segments: Vec<String> = match key.strip_prefix('/') {
+        Some(pointer) => pointer.split('/').map(|segment| segment.replace("~1", "/").replace("~0", "~")).collect(),
+        None => key.split('.').map(|segment| segment.to_string()).collect(),
+    };
+    let (first, rest) = segments.split_first()?;
+    let mut value = map.get(first)?;
+    for segment in rest {
+        value = match value {
+            Value::Map(entries) => entries.get(&Value::String(segment.clone()))?,
+            Value::Seq(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value.clone())
+}
+
+fn invalid_output<E: ToString>(key: &str, err: E) -> cdumay_error::Error {
+    InvalidOutput::new()
+        .set_message(format!("Invalid output '{}': {}", key, err.to_string()))
+        .set_details(BTreeMap::from([("key".to_string(), Value::String(key.to_string()))]))
+        .into()
 }
 
-pub trait TaskExec: TaskInfo {
+pub trait TaskExec: TaskInfo + Sized {
     fn entrypoint() -> String {
         Self::path()
     }
+    /// JSON Schema of the message params, published by [`Registry::schemas`](crate::Registry::schemas).
+    /// None by default: the schema is not guessed from [`TaskInfo::Params`], it is either written
+    /// by hand or generated, e.g. using `schemars::schema_for!` on a params type deriving both
+    /// `Deserialize` and `JsonSchema`, which reads the same serde attributes.
+    fn params_schema() -> Option<serde_json::Value> {
+        None
+    }
     /***********************************************************************************************
-    // Method to check required parameters ( Message.params() <=> Task::required_params() )
+    // Method to check required parameters ( Message.params() <=> TaskInfo::Params )
      */
     fn check_required_params(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        self.params()?;
         Ok(self.result())
     }
     /***********************************************************************************************
@@ -80,12 +360,58 @@ pub trait TaskExec: TaskInfo {
     fn _run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
         *self.result_mut() = &self.result() + &self._set_status(Status::Running)?;
         debug!("{}: {}", self.label(Some("Run")), self.result());
-        self.run()
+        match self.timeout() {
+            Some(timeout) => timeout.run(self),
+            None => self.run(),
+        }
     }
     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
     }
     /***********************************************************************************************
+    // Timeout - Maximum duration of run(). The task is moved to a watchdog thread which is left
+    // behind if the timeout expires, the task is then marked as timed out (see Timeout)
+     */
+    /// Maximum duration of [`TaskExec::run`], none by default. A timed out task keeps the
+    /// [`Status::TimedOut`] status and is not retried.
+    ///
+    /// The task is moved to a watchdog thread, hence the `Send + 'static` bounds of
+    /// [`Timeout::new`](crate::Timeout::new). Meanwhile `self` is a placeholder built using
+    /// [`TaskInfo::new`]: it holds the message, the status and the result, any other field (like
+    /// the extra fields of a task using `#[derive(TaskInfo)]`) has its `Default` value. The task is
+    /// put back once run. A timed out task is not: the placeholder remains, the changes made by the
+    /// task to itself are lost and its thread is left running until `run` returns.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use cdumay_job::errors::TaskTimeout;
+    /// use cdumay_job::{define_task, MessageBuilder, RetryPolicy, Status, TaskExec, TaskInfo, Timeout};
+    ///
+    /// define_task!(Sleepy);
+    ///
+    /// impl TaskExec for Sleepy {
+    ///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+    ///         std::thread::sleep(Duration::from_millis(500));
+    ///         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    ///     }
+    ///     fn timeout(&self) -> Option<Timeout<Self>> {
+    ///         Some(Timeout::new(Duration::from_millis(10)))
+    ///     }
+    ///     fn retry_policy(&self) -> Option<RetryPolicy> {
+    ///         Some(RetryPolicy::new(3))
+    ///     }
+    /// }
+    ///
+    /// let mut task = Sleepy::new(&MessageBuilder::new("sleepy".to_string()).build(), None);
+    /// let result = task.execute(None);
+    /// assert_eq!(result.retcode, TaskTimeout::kind.code());
+    /// assert_eq!(task.status(), Status::TimedOut);
+    /// assert_eq!(task.result().retval.get("attempts"), Some(&serde_value::Value::U32(1)));
+    /// ```
+    fn timeout(&self) -> Option<Timeout<Self>> {
+        None
+    }
+    /***********************************************************************************************
     // Post Run - Trigger launched just after running the task
      */
     fn _post_run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
@@ -99,10 +425,15 @@ pub trait TaskExec: TaskInfo {
     // On Error - Trigger raised if any error is raised
      */
     fn _on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
-        *self.result_mut() = &self.result() + &self._set_status(Status::Failed)?;
+        // a timed out task keeps its status
+        if self.status() != Status::TimedOut {
+            *self.result_mut() = &self.result() + &self._set_status(Status::Failed)?;
+        }
         *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
         error!("{}: {}", self.label(Some("Failed")), self.result());
-        self.on_error(error)
+        let result = self.on_error(error)?;
+        self.save()?;
+        Ok(result)
     }
     fn on_error(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
         Ok(cdumay_result::ResultBuilder::from(&self.message())
@@ -115,24 +446,118 @@ pub trait TaskExec: TaskInfo {
     fn _on_success(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
         *self.result_mut() = &self.result() + &self._set_status(Status::Success)?;
         info!("{}: {}", self.label(Some("Success")), self.result());
-        self.on_success()
+        let result = self.on_success()?;
+        self.save()?;
+        Ok(result)
     }
     fn on_success(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
         Ok(self.result())
     }
     /***********************************************************************************************
-    // Unsafe Execute - Method to call to get a Result of the task execution.
+    // Unsafe Execute - Method to call to get a Result of the task execution. Skipped and cancelled
+    // tasks are not run.
     // NOTE: the trigger on_error is not called!
      */
     fn unsafe_execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
         if let Some(data) = result {
             *self.result_mut() = &self.result() + &data;
         }
+        if matches!(self.status(), Status::Skipped | Status::Cancelled) {
+            debug!("{}: {}", self.label(Some(&self.status().to_string())), self.result());
+            return Ok(self.result());
+        }
         *self.result_mut() = &self.result() + &self._post_init()?;
+        let policy = self.retry_policy();
+        let mut attempt = 1;
+        loop {
+            if policy.is_some() {
+                self.result_mut().retval.insert("attempts".to_string(), Value::U32(attempt));
+            }
+            self._check_cancelled()?;
+            match self._attempt() {
+                Ok(()) => break,
+                Err(err) => match &policy {
+                    // the thread of a timed out attempt may still be running, it is not retried
+                    Some(policy) if err.kind != TaskCancelled::kind && err.kind != TaskTimeout::kind && policy.should_retry(attempt, &err) => {
+                        *self.result_mut() = &self.result() + &self._retry(attempt, &err)?;
+                        thread::sleep(policy.delay(attempt));
+                        attempt += 1;
+                    }
+                    _ => return Err(err),
+                },
+            }
+        }
+        self._on_success()
+    }
+    fn _attempt(&mut self) -> cdumay_error::Result<()> {
         *self.result_mut() = &self.result() + &self._pre_run()?;
         *self.result_mut() = &self.result() + &self._run()?;
         *self.result_mut() = &self.result() + &self._post_run()?;
-        self._on_success()
+        Ok(())
+    }
+    /***********************************************************************************************
+    // Retry - Policy used by unsafe_execute to run again pre_run, run and post_run on error, the
+    // trigger on_retry is launched before each new attempt
+     */
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+    fn _retry(&mut self, attempt: u32, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Retrying)?;
+        warn!("{}: attempt {} failed: {}", self.label(Some("Retry")), attempt, error);
+        self.on_retry(attempt, error)
+    }
+    fn on_retry(&mut self, _attempt: u32, _error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    /***********************************************************************************************
+    // Cancel - Cooperative cancellation, the token is checked before each attempt and can be
+    // checked by run() (see CancellationToken::check). A cancelled task ends with the status
+    // cancelled, the trigger on_cancel is launched instead of on_error
+     */
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        None
+    }
+    fn _check_cancelled(&self) -> cdumay_error::Result<()> {
+        match self.cancellation_token() {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+    fn _on_cancel(&mut self, error: &cdumay_error::Error) -> cdumay_error::Result<cdumay_result::Result> {
+        *self.result_mut() = &self.result() + &self._set_status(Status::Cancelled)?;
+        *self.result_mut() = &self.result() + &cdumay_result::Result::from(error.clone());
+        warn!("{}: {}", self.label(Some("Cancelled")), self.result());
+        let result = self.on_cancel()?;
+        self.save()?;
+        Ok(result)
+    }
+    fn on_cancel(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(self.result())
+    }
+    /***********************************************************************************************
+    // Compensate - Trigger which undoes a succeeded task, launched in reverse order by operations
+    // which failed (see Operation::compensate_on_error)
+     */
+    fn _compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        debug!("{}: {}", self.label(Some("Compensate")), self.result());
+        match self.compensate() {
+            Ok(result) => {
+                *self.result_mut() = &self.result() + &result;
+                *self.result_mut() = &self.result() + &self._set_status(Status::Compensated)?;
+                info!("{}: {}", self.label(Some("Compensated")), self.result());
+                Ok(result)
+            }
+            Err(err) => {
+                *self.result_mut() = &self.result() + &self._set_status(Status::CompensationFailed)?;
+                *self.result_mut() = &self.result() + &cdumay_result::Result::from(err.clone());
+                error!("{}: {}", self.label(Some("CompensationFailed")), self.result());
+                Err(err)
+            }
+        }
+    }
+    fn compensate(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
     }
     /***********************************************************************************************
     // Execute - The method used by the registry
@@ -140,6 +565,10 @@ pub trait TaskExec: TaskInfo {
     fn execute(&mut self, result: Option<cdumay_result::Result>) -> cdumay_result::Result {
         match self.unsafe_execute(result) {
             Ok(result) => result,
+            Err(err) if err.kind == TaskCancelled::kind => match self._on_cancel(&err) {
+                Ok(result) => result,
+                Err(err) => cdumay_result::Result::from(err),
+            },
             Err(err) => match self._on_error(&err) {
                 Ok(result) => result,
                 Err(err) => cdumay_result::Result::from(err),
@@ -148,20 +577,67 @@ pub trait TaskExec: TaskInfo {
     }
     /***********************************************************************************************
     // Status - Methods to update the status of the task. it can be overwrite to perform action such
-    // as database save ...
+    // as database save ... Illegal transitions (see Status::can_transition_to) raise an error.
      */
     fn _set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        self.status().check_transition_to(&status)?;
         debug!("{}: status updated '{}' -> '{}'", self.label(Some("SetStatus")), self.status(), &status);
-        self.set_status(status)
+        let from = self.status();
+        let result = self.set_status(status.clone())?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
+    }
+    // Force the status without checking the transition, reserved to admin or recovery tools
+    fn force_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
+        warn!("{}: status forced '{}' -> '{}'", self.label(Some("ForceStatus")), self.status(), &status);
+        let from = self.status();
+        let result = self.set_status(status.clone())?;
+        self.record_transition(&from, &status)?;
+        Ok(result)
     }
     fn set_status(&mut self, status: Status) -> cdumay_error::Result<cdumay_result::Result> {
         *self.status_mut() = status;
+        self.save()?;
         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
     }
     /***********************************************************************************************
-    // Send: Send back to kafka (used by operations)
+    // Store - Snapshots are saved by set_status and finalize into the configured store, if any.
+    // They are saved again once the final result is merged, on success, on error and on cancel.
+    // Each status change made by _set_status or force_status is recorded as a transition
+     */
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        None
+    }
+    fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot::new(Self::entrypoint(), self.status(), self.message(), self.result())
+    }
+    fn save(&self) -> cdumay_error::Result<()> {
+        match self.store() {
+            Some(store) => store.save(&self.snapshot()),
+            None => Ok(()),
+        }
+    }
+    fn record_transition(&self, from: &Status, to: &Status) -> cdumay_error::Result<()> {
+        match self.store() {
+            Some(store) => store.record_transition(&self.message().uuid, from, to),
+            None => Ok(()),
+        }
+    }
+    /***********************************************************************************************
+    // Send: Send back to kafka (used by operations). The message is published on the topic named
+    // after the entrypoint (see topic) using the configured transport, if any
      */
+    fn transport(&self) -> Option<Arc<dyn Transport>> {
+        None
+    }
+    fn topic(&self) -> String {
+        Self::entrypoint()
+    }
     fn send(&self, result: Option<cdumay_result::Result>) -> cdumay_error::Result<cdumay_result::Result> {
+        if let Some(transport) = self.transport() {
+            debug!("{}: publish on '{}'", self.label(Some("Send")), self.topic());
+            return publish_result(transport.as_ref(), &self.topic(), &self.message(), result);
+        }
         match result {
             Some(result) => Ok(cdumay_result::ResultBuilder::from(&self.message()).build().add(&result)),
             None => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
@@ -171,6 +647,7 @@ pub trait TaskExec: TaskInfo {
     // Finalize: Finalize the task, use by operation to perform database save or so one.
      */
     fn finalize(&self) -> cdumay_error::Result<cdumay_result::Result> {
+        self.save()?;
         Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
     }
 }
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use serde_value::Value;
+
+use crate::errors::{TaskPanicked, TaskTimeout};
+use crate::{Status, TaskExec};
+
+type TimeoutRunner<T> = fn(&mut T, Duration) -> cdumay_error::Result<cdumay_result::Result>;
+
+/// Maximum duration of the run of a task, returned by [`TaskExec::timeout`](crate::TaskExec::timeout).
+/// The task is moved to a watchdog thread while it runs, only tasks which are `Send + 'static` can
+/// have a timeout.
+pub struct Timeout<T> {
+    duration: Duration,
+    runner: TimeoutRunner<T>,
+}
+
+impl<T: TaskExec + Send + 'static> Timeout<T> {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            runner: run_with_timeout::<T>,
+        }
+    }
+}
+
+impl<T> Timeout<T> {
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    pub(crate) fn run(&self, task: &mut T) -> cdumay_error::Result<cdumay_result::Result> {
+        (self.runner)(task, self.duration)
+    }
+}
+
+impl<T> Clone for Timeout<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Timeout<T> {}
+
+impl<T> fmt::Debug for Timeout<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timeout").field("duration", &self.duration).finish_non_exhaustive()
+    }
+}
+
+// Runs the task on a watchdog thread, a placeholder takes its place until the task is put back
+fn run_with_timeout<T: TaskExec + Send + 'static>(task: &mut T, timeout: Duration) -> cdumay_error::Result<cdumay_result::Result> {
+    let mut placeholder = T::new(&task.message(), Some(task.result()));
+    *placeholder.status_mut() = task.status();
+    let mut running = mem::replace(task, placeholder);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = running.run();
+        let _ = sender.send((running, result));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok((running, result)) => {
+            *task = running;
+            result
+        }
+        Err(RecvTimeoutError::Timeout) => {
+            *task.result_mut() = &task.result() + &task._set_status(Status::TimedOut)?;
+            Err(TaskTimeout::new()
+                .set_message(format!("Task timed out after {:?}", timeout))
+                .set_details(BTreeMap::from([(
+                    "timeout_ms".to_string(),
+                    Value::U64(timeout.as_millis() as u64),
+                )]))
+                .into())
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(TaskPanicked::new()
+            .set_message("Task panicked during run".to_string())
+            .into()),
+    }
+}
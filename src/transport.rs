@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_value::Value;
+
+use crate::Message;
+use crate::errors::TransportFailure;
+
+/// Delivers messages to the queue (or topic) named after the entrypoint of the task to run.
+pub trait Transport: Debug + Send + Sync {
+    fn publish(&self, topic: &str, message: &Message) -> cdumay_error::Result<()>;
+}
+
+// Merges the result into the message and publish it, used by TaskExec::send and
+// Operation::launch_next
+pub(crate) fn publish_result(
+    transport: &dyn Transport,
+    topic: &str,
+    message: &Message,
+    result: Option<cdumay_result::Result>,
+) -> cdumay_error::Result<cdumay_result::Result> {
+    let result = match result {
+        Some(result) => &cdumay_result::ResultBuilder::from(message).build() + &result,
+        None => cdumay_result::ResultBuilder::from(message).build(),
+    };
+    let mut message = message.clone();
+    message.result = result.clone();
+    transport.publish(topic, &message)?;
+    Ok(result)
+}
+
+fn transport_error(message: String, topic: &str) -> cdumay_error::Error {
+    TransportFailure::new()
+        .set_message(message)
+        .set_details(BTreeMap::from([("topic".to_string(), Value::String(topic.to_string()))]))
+        .into()
+}
+
+/// [`Transport`] keeping messages in memory, mostly useful for tests.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use cdumay_job::{define_task, MemoryTransport, MessageBuilder, Operation, SequentialOperation, Status, TaskExec, TaskInfo};
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {}
+///
+/// let transport = Arc::new(MemoryTransport::default());
+/// let mut operation = SequentialOperation::new(&MessageBuilder::new("greetings".to_string()).build(), None)
+///     .with_tasks(vec![
+///         Hello::new(&MessageBuilder::new(Hello::entrypoint()).build(), None),
+///         Hello::new(&MessageBuilder::new(Hello::entrypoint()).build(), None),
+///     ])
+///     .with_transport(transport.clone());
+///
+/// operation.launch(None).unwrap();
+/// let message = transport.receive(&Hello::entrypoint()).unwrap();
+/// assert_eq!(message.uuid, operation.tasks()[0].message().uuid);
+///
+/// // a consumer runs the task and reports back to the operation
+/// let mut task = Hello::new(&message, None);
+/// let result = task.execute(None);
+/// operation.launch_next(Some(task), Some(result)).unwrap();
+/// let message = transport.receive(&Hello::entrypoint()).unwrap();
+/// assert_eq!(message.uuid, operation.tasks()[1].message().uuid);
+///
+/// // the last task is reported to an operation rebuilt from its message, which is still pending
+/// let mut task = Hello::new(&message, None);
+/// let result = task.execute(None);
+/// let mut operation = SequentialOperation::new(&operation.message(), None).with_tasks(operation.tasks().clone());
+/// assert_eq!(operation.status(), Status::Pending);
+/// operation.launch_next(Some(task), Some(result)).unwrap();
+/// assert_eq!(operation.status(), Status::Success);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    queues: Mutex<BTreeMap<String, VecDeque<Message>>>,
+}
+
+impl MemoryTransport {
+    /// Pops the oldest message of the topic.
+    pub fn receive(&self, topic: &str) -> Option<Message> {
+        self.queues.lock().ok()?.get_mut(topic)?.pop_front()
+    }
+    pub fn len(&self, topic: &str) -> usize {
+        self.queues
+            .lock()
+            .map(|queues| queues.get(topic).map_or(0, VecDeque::len))
+            .unwrap_or(0)
+    }
+    pub fn is_empty(&self, topic: &str) -> bool {
+        self.len(topic) == 0
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn publish(&self, topic: &str, message: &Message) -> cdumay_error::Result<()> {
+        self.queues
+            .lock()
+            .map_err(|err| transport_error(err.to_string(), topic))?
+            .entry(topic.to_string())
+            .or_default()
+            .push_back(message.clone());
+        Ok(())
+    }
+}
+
+/// [`Transport`] writing each message as a JSON file in a sub directory of the spool directory
+/// named after the topic.
+///
+/// ```rust
+/// use cdumay_job::{MessageBuilder, SpoolTransport, Transport};
+///
+/// let spool = SpoolTransport::new(std::env::temp_dir().join(format!("spool-{}", uuid::Uuid::new_v4())));
+/// let message = MessageBuilder::new("hello".to_string()).build();
+/// spool.publish("hello", &message).unwrap();
+///
+/// assert_eq!(spool.receive("hello").unwrap().unwrap().uuid, message.uuid);
+/// assert!(spool.receive("hello").unwrap().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpoolTransport {
+    directory: PathBuf,
+}
+
+impl SpoolTransport {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+    // entrypoints contain characters such as ':' which are not allowed in file names
+    fn topic_directory(&self, topic: &str) -> PathBuf {
+        self.directory.join(
+            topic
+                .chars()
+                .map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                    true => c,
+                    false => '_',
+                })
+                .collect::<String>(),
+        )
+    }
+    /// Reads and removes the oldest message of the topic.
+    pub fn receive(&self, topic: &str) -> cdumay_error::Result<Option<Message>> {
+        let directory = self.topic_directory(topic);
+        if !directory.exists() {
+            return Ok(None);
+        }
+        let mut files = fs::read_dir(&directory)
+            .map_err(|err| transport_error(err.to_string(), topic))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        match files.first() {
+            Some(path) => {
+                let data = fs::read(path).map_err(|err| transport_error(err.to_string(), topic))?;
+                fs::remove_file(path).map_err(|err| transport_error(err.to_string(), topic))?;
+                Ok(Some(
+                    serde_json::from_slice(&data).map_err(|err| transport_error(err.to_string(), topic))?,
+                ))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl Transport for SpoolTransport {
+    fn publish(&self, topic: &str, message: &Message) -> cdumay_error::Result<()> {
+        let directory = self.topic_directory(topic);
+        fs::create_dir_all(&directory).map_err(|err| transport_error(err.to_string(), topic))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let name = format!("{:032}-{}", timestamp, message.uuid);
+        let data = serde_json::to_vec(message).map_err(|err| transport_error(err.to_string(), topic))?;
+        // the file is renamed once written, so that readers never see a partial message
+        let tmp = directory.join(format!("{}.tmp", name));
+        fs::write(&tmp, data).map_err(|err| transport_error(err.to_string(), topic))?;
+        fs::rename(&tmp, directory.join(format!("{}.json", name))).map_err(|err| transport_error(err.to_string(), topic))
+    }
+}
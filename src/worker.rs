@@ -0,0 +1,118 @@
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use log::{debug, error};
+
+use crate::errors::ChannelClosed;
+use crate::{Message, Registry};
+
+/// Source of the messages consumed by a [`Worker`].
+pub trait MessageSource: Send + Sync {
+    /// Waits for the next message, `None` means that the source is closed.
+    fn receive(&self) -> Option<Message>;
+}
+
+/// Destination of the results produced by a [`Worker`].
+pub trait ResultSink: Send + Sync {
+    fn publish(&self, result: cdumay_result::Result) -> cdumay_error::Result<()>;
+}
+
+/// In-memory [`MessageSource`] reading a channel.
+pub struct ChannelSource {
+    receiver: Mutex<Receiver<Message>>,
+}
+
+impl From<Receiver<Message>> for ChannelSource {
+    fn from(receiver: Receiver<Message>) -> ChannelSource {
+        ChannelSource { receiver: Mutex::new(receiver) }
+    }
+}
+
+impl MessageSource for ChannelSource {
+    fn receive(&self) -> Option<Message> {
+        self.receiver.lock().ok()?.recv().ok()
+    }
+}
+
+/// In-memory [`ResultSink`] writing into a channel.
+pub struct ChannelSink {
+    sender: Sender<cdumay_result::Result>,
+}
+
+impl From<Sender<cdumay_result::Result>> for ChannelSink {
+    fn from(sender: Sender<cdumay_result::Result>) -> ChannelSink {
+        ChannelSink { sender }
+    }
+}
+
+impl ResultSink for ChannelSink {
+    fn publish(&self, result: cdumay_result::Result) -> cdumay_error::Result<()> {
+        self.sender.send(result).map_err(|err| {
+            ChannelClosed::new()
+                .set_message(format!("Failed to publish result: {}", err))
+                .into()
+        })
+    }
+}
+
+/// Runs the messages of a [`MessageSource`] using the tasks of a [`Registry`] on a bounded pool of
+/// threads, results are published into a [`ResultSink`].
+///
+/// ```rust
+/// use std::sync::mpsc;
+/// use cdumay_job::{define_task, ChannelSink, ChannelSource, MessageBuilder, Registry, TaskExec, Worker};
+///
+/// define_task!(Hello);
+/// impl TaskExec for Hello {}
+///
+/// let (messages, receiver) = mpsc::channel();
+/// let (sender, results) = mpsc::channel();
+/// for _ in 0..3 {
+///     messages.send(MessageBuilder::new(Hello::entrypoint()).build()).unwrap();
+/// }
+/// drop(messages);
+///
+/// Worker::new(Registry::default().register::<Hello>(), ChannelSource::from(receiver), ChannelSink::from(sender))
+///     .concurrency(2)
+///     .run();
+/// assert_eq!(results.try_iter().filter(|result| !result.is_error()).count(), 3);
+/// ```
+pub struct Worker<S: MessageSource, R: ResultSink> {
+    registry: Registry,
+    source: S,
+    sink: R,
+    concurrency: usize,
+}
+
+impl<S: MessageSource, R: ResultSink> Worker<S, R> {
+    pub fn new(registry: Registry, source: S, sink: R) -> Self {
+        Self {
+            registry,
+            source,
+            sink,
+            concurrency: 1,
+        }
+    }
+    /// Number of messages processed at the same time, defaults to 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Processes messages until the source is closed.
+    pub fn run(&self) {
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency {
+                scope.spawn(|| self.consume());
+            }
+        });
+    }
+    fn consume(&self) {
+        while let Some(message) = self.source.receive() {
+            debug!("{}[{}] - Worker: message received", message.entrypoint, message.uuid);
+            if let Err(err) = self.sink.publish(self.registry.execute(&message)) {
+                error!("{}[{}] - Worker: {}", message.entrypoint, message.uuid, err);
+            }
+        }
+    }
+}
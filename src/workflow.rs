@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use cdumay_error::Result;
+use log::debug;
+use serde_value::Value;
+
+use crate::errors::{InvalidWorkflow, TaskPanicked};
+use crate::operation::{end_run, OperationSettings};
+use crate::{CancellationToken, Condition, ExecutionMode, FailurePolicy, Message, Operation, Status, TaskExec, TaskSnapshot, TaskStore, Transport};
+
+/// An [`Operation`] whose tasks declare the tasks they depend on. Tasks run as soon as their
+/// dependencies succeeded and receive the merged `retval` of their dependencies. The graph is
+/// checked on [`Operation::build`].
+///
+/// A task can have a [`Condition`], evaluated against the merged result of its dependencies.
+///
+/// Tasks run one at a time unless the execution mode is
+/// [`ExecutionMode::Parallel`](crate::ExecutionMode::Parallel). Using
+/// [`FailurePolicy::CollectAll`](crate::FailurePolicy::CollectAll), tasks depending on a failed
+/// task are skipped.
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use cdumay_job::errors::InvalidWorkflow;
+/// use cdumay_job::{define_task, MessageBuilder, Operation, Status, TaskExec, TaskInfo, Workflow};
+/// use serde_value::Value;
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         // each step sees the retval of its dependencies, and adds its own entrypoint
+///         let seen = self.result().retval.keys().cloned().collect::<Vec<String>>().join(",");
+///         Ok(cdumay_result::ResultBuilder::from(&self.message())
+///             .retval(BTreeMap::from([(self.message().entrypoint, Value::String(seen))]))
+///             .build())
+///     }
+/// }
+///
+/// let step = |name: &str| Step::new(&MessageBuilder::new(name.to_string()).build(), None);
+/// let mut workflow = Workflow::new(&MessageBuilder::new("release".to_string()).build(), None)
+///     .task("publish", step("publish"), &["test", "docs"])
+///     .task("test", step("test"), &["compile"])
+///     .task("docs", step("docs"), &["compile"])
+///     .task("compile", step("compile"), &[]);
+/// workflow.build().unwrap();
+///
+/// let result = workflow.execute(None);
+/// assert_eq!(workflow.status(), Status::Success);
+/// assert_eq!(result.retval.get("publish"), Some(&Value::String("compile,docs,test".to_string())));
+///
+/// let mut cyclic = Workflow::new(&MessageBuilder::new("cyclic".to_string()).build(), None)
+///     .task("a", step("a"), &["b"])
+///     .task("b", step("b"), &["a"]);
+/// assert!(cyclic.build().is_err());
+///
+/// // the graph is not part of the snapshots, a workflow cannot be rebuilt from them alone
+/// let snapshots = workflow.tasks().iter().map(|task| task.snapshot()).collect();
+/// match Workflow::<Step>::restore(&workflow.message(), snapshots) {
+///     Err(err) => assert_eq!(err.kind, InvalidWorkflow::kind),
+///     Ok(_) => panic!("workflow restored without its graph"),
+/// }
+/// ```
+///
+/// An interrupted workflow is resumed by applying its snapshots to the workflow rebuilt using
+/// [`Workflow::task`], finished tasks are not run again:
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use cdumay_job::{define_task, Message, MessageBuilder, Operation, Status, TaskExec, TaskInfo, Workflow};
+///
+/// static RUNS: AtomicUsize = AtomicUsize::new(0);
+///
+/// define_task!(Step);
+///
+/// impl TaskExec for Step {
+///     fn run(&mut self) -> cdumay_error::Result<cdumay_result::Result> {
+///         // the second run fails the first time
+///         match RUNS.fetch_add(1, Ordering::SeqCst) {
+///             1 => Err(cdumay_error::Error::default()),
+///             _ => Ok(cdumay_result::ResultBuilder::from(&self.message()).build()),
+///         }
+///     }
+/// }
+///
+/// let message = MessageBuilder::new("release".to_string()).build();
+/// let compile = MessageBuilder::new("compile".to_string()).build();
+/// let test = MessageBuilder::new("test".to_string()).build();
+/// let rebuild = |message: &Message| {
+///     Workflow::new(message, None)
+///         .task("compile", Step::new(&compile, None), &[])
+///         .task("test", Step::new(&test, None), &["compile"])
+/// };
+/// let mut workflow = rebuild(&message);
+/// workflow.execute(None);
+/// assert_eq!(workflow.status(), Status::Failed);
+///
+/// let mut snapshots = vec![workflow.snapshot()];
+/// snapshots.extend(workflow.tasks().iter().map(|task| task.snapshot()));
+/// let mut resumed = rebuild(&message).with_snapshots(snapshots);
+/// assert_eq!(resumed.tasks()[0].status(), Status::Success);
+/// resumed.execute(None);
+/// assert_eq!(resumed.status(), Status::Success);
+/// assert_eq!(RUNS.load(Ordering::SeqCst), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Workflow<T: TaskExec> {
+    message: Message,
+    status: Status,
+    result: cdumay_result::Result,
+    tasks: Vec<T>,
+    names: Vec<String>,
+    dependencies: Vec<Vec<String>>,
+    conditions: BTreeMap<String, Condition>,
+    execution_mode: ExecutionMode,
+    settings: OperationSettings,
+}
+
+impl<T: TaskExec> Workflow<T> {
+    /// Adds a task identified by `name`, which depends on the tasks named in `dependencies`.
+    pub fn task(mut self, name: &str, task: T, dependencies: &[&str]) -> Self {
+        self.tasks.push(task);
+        self.names.push(name.to_string());
+        self.dependencies
+            .push(dependencies.iter().map(|dependency| dependency.to_string()).collect());
+        self
+    }
+    /// Sets the condition of the task identified by `name`, evaluated against the merged result
+    /// of its dependencies.
+    pub fn with_condition(mut self, name: &str, condition: Condition) -> Self {
+        self.conditions.insert(name.to_string(), condition);
+        self
+    }
+    pub fn with_execution_mode(mut self, execution_mode: ExecutionMode) -> Self {
+        self.execution_mode = execution_mode;
+        self
+    }
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.settings.cancellation_token = Some(token);
+        self
+    }
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.settings.transport = Some(transport);
+        self
+    }
+    pub fn with_store(mut self, store: Arc<dyn TaskStore>) -> Self {
+        self.settings.store = Some(store);
+        self
+    }
+    /// Applies the snapshots of an interrupted run (see [`TaskStore`]) to the workflow rebuilt
+    /// using [`Workflow::task`]: the snapshot sharing the uuid of the message restores the
+    /// workflow itself, the others restore the task sharing the uuid of their message. Finished
+    /// tasks are skipped on the next execution.
+    pub fn with_snapshots(mut self, snapshots: Vec<TaskSnapshot>) -> Self {
+        for snapshot in snapshots {
+            if snapshot.uuid == self.message.uuid {
+                self.status = snapshot.status;
+                self.result = snapshot.result;
+            } else if let Some(task) = self.tasks.iter_mut().find(|task| task.message().uuid == snapshot.uuid) {
+                *task.status_mut() = snapshot.status;
+                *task.result_mut() = snapshot.result;
+            }
+        }
+        self
+    }
+    /// Indexes of the dependencies of each task.
+    fn dependency_indexes(&self) -> Result<Vec<Vec<usize>>> {
+        // tasks which were not added using Workflow::task (e.g. using tasks_mut) have no graph
+        if self.names.len() != self.tasks.len() {
+            return Err(self.invalid(
+                "The dependencies of the tasks are unknown, tasks must be added using Workflow::task".to_string(),
+                self.tasks.iter().map(|task| task.message().entrypoint).collect(),
+            ));
+        }
+        let mut indexes = BTreeMap::new();
+        for (index, name) in self.names.iter().enumerate() {
+            if indexes.insert(name.as_str(), index).is_some() {
+                return Err(self.invalid(format!("Duplicate task '{}'", name), vec![name.clone()]));
+            }
+        }
+        self.dependencies
+            .iter()
+            .enumerate()
+            .map(|(index, dependencies)| {
+                dependencies
+                    .iter()
+                    .map(|dependency| match indexes.get(dependency.as_str()) {
+                        Some(found) => Ok(*found),
+                        None => Err(self.invalid(
+                            format!("Task '{}' depends on unknown task '{}'", self.names[index], dependency),
+                            vec![self.names[index].clone(), dependency.clone()],
+                        )),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// Sorts the tasks so that each one comes after its dependencies, fails on cycle.
+    pub fn topological_order(&self) -> Result<Vec<usize>> {
+        let dependencies = self.dependency_indexes()?;
+        let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut ready: VecDeque<usize> = (0..remaining.len()).filter(|index| remaining[*index] == 0).collect();
+        let mut order = vec![];
+        while let Some(index) = ready.pop_front() {
+            order.push(index);
+            for (dependent, dependencies) in dependencies.iter().enumerate() {
+                for _ in dependencies.iter().filter(|dependency| **dependency == index) {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        ready.push_back(dependent);
+                    }
+                }
+            }
+        }
+        match order.len() == self.tasks.len() {
+            true => Ok(order),
+            false => {
+                let cycle = (0..self.tasks.len())
+                    .filter(|index| remaining[*index] > 0)
+                    .map(|index| self.names[index].clone())
+                    .collect::<Vec<String>>();
+                Err(self.invalid(format!("Dependency cycle between {}", cycle.join(", ")), cycle))
+            }
+        }
+    }
+    fn invalid(&self, message: String, tasks: Vec<String>) -> cdumay_error::Error {
+        InvalidWorkflow::new()
+            .set_message(message)
+            .set_details(BTreeMap::from([(
+                "tasks".to_string(),
+                Value::Seq(tasks.into_iter().map(Value::String).collect()),
+            )]))
+            .into()
+    }
+}
+
+impl<T: TaskExec + Clone + Send> Operation for Workflow<T> {
+    type TasksItems = T;
+
+    fn pre_build(&mut self) -> Result<cdumay_result::Result> {
+        self.topological_order()?;
+        Ok(cdumay_result::ResultBuilder::from(&self.message()).build())
+    }
+    fn build_tasks(&self) -> Vec<T> {
+        self.tasks.clone()
+    }
+    fn execution_mode(&self) -> ExecutionMode {
+        self.execution_mode.clone()
+    }
+    fn condition(&self, task: &T) -> Option<Condition> {
+        let uuid = task.message().uuid;
+        let index = self.tasks.iter().position(|item| item.message().uuid == uuid)?;
+        self.conditions.get(self.names.get(index)?).cloned()
+    }
+    fn cancellation_token(&self) -> Option<CancellationToken> {
+        self.settings.cancellation_token.clone()
+    }
+    fn transport(&self) -> Option<Arc<dyn Transport>> {
+        self.settings.transport.clone()
+    }
+    fn store(&self) -> Option<Arc<dyn TaskStore>> {
+        self.settings.store.clone()
+    }
+    fn run(&mut self) -> Result<cdumay_result::Result> {
+        let order = self.topological_order()?;
+        let dependencies = self.dependency_indexes()?;
+        let (concurrency, on_failure) = match self.execution_mode() {
+            ExecutionMode::Sequential => (1, FailurePolicy::FailFast),
+            ExecutionMode::Parallel { concurrency, on_failure } => (concurrency.max(1), on_failure),
+        };
+        let base = self.result();
+        let message = self.message();
+
+        // tasks are moved to their thread while running, then put back in their slot
+        let mut slots: Vec<Option<T>> = mem::take(&mut self.tasks).into_iter().map(Some).collect();
+        let mut done = vec![false; slots.len()];
+        let mut outputs: Vec<Option<cdumay_result::Result>> = vec![None; slots.len()];
+        for (index, task) in slots.iter().flatten().enumerate() {
+            if matches!(task.status(), Status::Success | Status::Skipped) {
+                done[index] = true;
+                outputs[index] = Some(task.result());
+            }
+        }
+        let mut errors = vec![];
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let mut running = 0;
+            loop {
+                for index in order.iter().copied() {
+                    if running >= concurrency
+                        || (on_failure == FailurePolicy::FailFast && !errors.is_empty())
+                        || self._check_cancelled().is_err()
+                    {
+                        break;
+                    }
+                    if done[index] || !dependencies[index].iter().all(|dependency| done[*dependency]) {
+                        continue;
+                    }
+                    let Some(mut task) = slots[index].take() else {
+                        continue;
+                    };
+                    if dependencies[index].iter().any(|dependency| outputs[*dependency].is_none()) {
+                        debug!("{}: a dependency of '{}' failed", self.label(Some("Skip")), self.names[index]);
+                        if task.status().can_transition_to(&Status::Skipped) {
+                            let _ = task._set_status(Status::Skipped);
+                        }
+                        slots[index] = Some(task);
+                        done[index] = true;
+                        continue;
+                    }
+                    let mut retval = base.retval.clone();
+                    for dependency in &dependencies[index] {
+                        if let Some(output) = &outputs[*dependency] {
+                            retval.extend(output.retval.clone());
+                        }
+                    }
+                    let input = cdumay_result::ResultBuilder::from(&message).retval(retval).build();
+                    // a task whose condition is not met is skipped, its dependents are run
+                    let condition = self.conditions.get(&self.names[index]);
+                    if condition.is_some_and(|condition| !condition.evaluate(&input, &message.metadata)) {
+                        debug!("{}: condition not met, skip '{}'", self.label(Some("Condition")), self.names[index]);
+                        if task.status().can_transition_to(&Status::Skipped) {
+                            let _ = task._set_status(Status::Skipped);
+                        }
+                        outputs[index] = Some(task.result());
+                        slots[index] = Some(task);
+                        done[index] = true;
+                        continue;
+                    }
+                    let sender = sender.clone();
+                    running += 1;
+                    scope.spawn(move || {
+                        let outcome = panic::catch_unwind(AssertUnwindSafe(|| task.unsafe_execute(Some(input))))
+                            .unwrap_or_else(|_| {
+                                Err(TaskPanicked::new()
+                                    .set_message("Task panicked during run".to_string())
+                                    .into())
+                            });
+                        let _ = sender.send((index, task, outcome));
+                    });
+                }
+                if running == 0 {
+                    break;
+                }
+                let Ok((index, task, outcome)) = receiver.recv() else {
+                    break;
+                };
+                running -= 1;
+                slots[index] = Some(task);
+                done[index] = true;
+                match outcome {
+                    Ok(output) => outputs[index] = Some(output),
+                    Err(err) => errors.push((index, err)),
+                }
+            }
+        });
+        self.tasks = slots.into_iter().flatten().collect();
+
+        let mut result = base;
+        for index in order {
+            if let Some(output) = &outputs[index] {
+                result = &result + output;
+            }
+        }
+        end_run(self, result, errors, done.iter().all(|done| *done), on_failure)
+    }
+
+    /// The dependencies of the tasks are not part of the snapshots, a workflow cannot be rebuilt
+    /// from them: an [`InvalidWorkflow`] error is returned. The workflow is rebuilt using
+    /// [`Workflow::task`] instead, then its snapshots are applied using [`Workflow::with_snapshots`].
+    fn restore(message: &Message, snapshots: Vec<TaskSnapshot>) -> Result<Self> {
+        let workflow = Self::new(message, None);
+        Err(workflow.invalid(
+            "A workflow cannot be restored from snapshots, use Workflow::with_snapshots".to_string(),
+            snapshots.into_iter().filter(|snapshot| snapshot.uuid != message.uuid).map(|snapshot| snapshot.entrypoint).collect(),
+        ))
+    }
+
+    fn new(message: &Message, result: Option<cdumay_result::Result>) -> Self {
+        Workflow {
+            message: message.clone(),
+            status: Status::Pending,
+            result: result.unwrap_or(message.result.clone()),
+            tasks: vec![],
+            names: vec![],
+            dependencies: vec![],
+            conditions: BTreeMap::new(),
+            execution_mode: ExecutionMode::Sequential,
+            settings: OperationSettings::default(),
+        }
+    }
+    fn status(&self) -> Status {
+        self.status.clone()
+    }
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
+    }
+    fn message(&self) -> Message {
+        self.message.clone()
+    }
+    fn message_mut(&mut self) -> &mut Message {
+        &mut self.message
+    }
+    fn result(&self) -> cdumay_result::Result {
+        self.result.clone()
+    }
+    fn result_mut(&mut self) -> &mut cdumay_result::Result {
+        &mut self.result
+    }
+    fn tasks(&self) -> &Vec<T> {
+        &self.tasks
+    }
+    fn tasks_mut(&mut self) -> &mut Vec<T> {
+        &mut self.tasks
+    }
+
+    // the next task is the next one in topological order
+    fn next(&mut self, task: &T) -> Option<T> {
+        let uuid = task.message().uuid;
+        let order = self.topological_order().ok()?;
+        let position = order.iter().position(|index| self.tasks[*index].message().uuid == uuid)?;
+        order.get(position + 1).map(|index| self.tasks[*index].clone())
+    }
+}